@@ -1,7 +1,12 @@
-use block_stm::test_utils::{aptos::*, BenchmarkInfos};
+use block_stm::test_utils::{
+    aptos::*,
+    report::{BenchmarkParameters, BenchmarkReport},
+    BenchmarkInfos,
+};
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use once_cell::sync::Lazy;
 use pprof::criterion::{Output, PProfProfiler};
-use std::time::Duration;
+use std::{sync::Mutex, time::Duration};
 const TXNS_NUM: usize = 10_000;
 
 fn install_logger() {
@@ -12,6 +17,23 @@ fn install_logger() {
         .with_max_level(tracing::Level::INFO)
         .try_init();
 }
+/// report of all benchmarks,exported after each benchmark
+static REPORT: Lazy<Mutex<BenchmarkReport>> = Lazy::new(Default::default);
+/// record samples to report,sequential execution is the baseline of speedup
+fn record(name: &str, parameters: BenchmarkParameters, infos: &mut BenchmarkInfos) {
+    let mut report = REPORT.lock().unwrap();
+    if name.contains("sequential") {
+        report.add_baseline(name, parameters, infos);
+    } else {
+        report.add(name, parameters, infos);
+    }
+    for path in ["./reports/aptos.json", "./reports/aptos.csv"] {
+        if let Err(e) = report.export(path) {
+            eprintln!("failed to export report to {}: {}", path, e);
+        }
+    }
+    infos.clear_infos();
+}
 fn log_benchmark_info(
     name: &str,
     accs: usize,
@@ -23,8 +45,16 @@ fn log_benchmark_info(
     {
         block_stm::rayon_info!("{} (accs={},txns={},cpus={})", name, accs, txns, cpus);
         block_stm::rayon_info!("{}", infos.mean());
-        infos.clear_infos();
     }
+    record(
+        name,
+        BenchmarkParameters {
+            accounts: accs,
+            txns,
+            cpus,
+        },
+        infos,
+    );
 }
 fn conflicting_level(c: &mut Criterion) {
     #[cfg(feature = "tracing")]
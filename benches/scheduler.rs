@@ -0,0 +1,26 @@
+use block_stm::test_utils::noop::execute_noop_block;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+const TXNS_NUM: usize = 10_000;
+/// every 4th transaction is aborted once,which exercises abort/incarnate transitions
+const ABORT_EVERY: usize = 4;
+
+/// scheduling overhead at increasing thread counts,
+/// run with and without `--features mutex_status` to compare transaction status implementations
+fn status(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scheduler");
+    group.throughput(Throughput::Elements(TXNS_NUM as u64));
+    for num_threads in [1, 2, 4, 8, 16, 32] {
+        group.bench_with_input(
+            BenchmarkId::new("noop block", num_threads),
+            &num_threads,
+            |b, &num_threads| b.iter(|| execute_noop_block(TXNS_NUM, num_threads, ABORT_EVERY)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = status);
+criterion_main!(benches);
@@ -1,8 +1,23 @@
-use block_stm::test_utils::{simulated::*, BenchmarkInfos};
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use block_stm::{
+    reorder::{reorder, ReorderStrategy},
+    test_utils::{
+        cost::{set_cost_model, CostModel},
+        report::{BenchmarkParameters, BenchmarkReport},
+        simulated::{workload::*, *},
+        BenchmarkInfo, BenchmarkInfos,
+    },
+    AdaptiveConcurrency, DenseMVMapConfig, MVMapConfig,
+};
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, Bencher, BenchmarkGroup, BenchmarkId,
+    Criterion, Throughput,
+};
+use once_cell::sync::Lazy;
 use pprof::criterion::{Output, PProfProfiler};
-use std::time::Duration;
+use std::{sync::Mutex, time::Duration};
 const TXNS_NUM: usize = 10_000;
+/// fixed seed,so that blocks are the same across runs
+const SEED: u64 = 42;
 
 fn install_logger() {
     let file_appender = tracing_appender::rolling::hourly("./logs", "simulated.log");
@@ -12,6 +27,40 @@ fn install_logger() {
         .with_max_level(tracing::Level::INFO)
         .try_init();
 }
+/// report of all benchmarks,exported after each benchmark
+static REPORT: Lazy<Mutex<BenchmarkReport>> = Lazy::new(Default::default);
+/// record samples to report,sequential execution is the baseline of speedup
+fn record(name: &str, parameters: BenchmarkParameters, infos: &mut BenchmarkInfos) {
+    let mut report = REPORT.lock().unwrap();
+    if name.contains("sequential") {
+        report.add_baseline(name, parameters, infos);
+    } else {
+        report.add(name, parameters, infos);
+    }
+    for path in ["./reports/simulated.json", "./reports/simulated.csv"] {
+        if let Err(e) = report.export(path) {
+            eprintln!("failed to export report to {}: {}", path, e);
+        }
+    }
+    infos.clear_infos();
+}
+/// bench routine which measures total time of `execute` and records its info
+fn bench_fn<'a, I: ?Sized, R>(
+    infos: &'a mut BenchmarkInfos,
+    mut execute: impl FnMut() -> (R, BenchmarkInfo) + 'a,
+) -> impl FnMut(&mut Bencher<WallTime>, &I) + 'a {
+    move |b: &mut Bencher<WallTime>, _: &I| {
+        b.iter_custom(|iters| {
+            let mut total = Duration::ZERO;
+            for _ in 0..iters {
+                let (_, info) = execute();
+                total += info.total_time;
+                infos.add_info(info);
+            }
+            total
+        })
+    }
+}
 fn log_benchmark_info(
     name: &str,
     accs: usize,
@@ -23,8 +72,32 @@ fn log_benchmark_info(
     {
         block_stm::rayon_info!("{} (accs={},txns={},cpus={})", name, accs, txns, cpus);
         block_stm::rayon_info!("{}", infos.mean());
-        infos.clear_infos();
     }
+    record(
+        name,
+        BenchmarkParameters {
+            accounts: accs,
+            txns,
+            cpus,
+        },
+        infos,
+    );
+}
+fn log_workload_info(name: &str, cpus: usize, infos: &mut BenchmarkInfos) {
+    #[cfg(feature = "tracing")]
+    {
+        block_stm::rayon_info!("{} (txns={},cpus={})", name, TXNS_NUM, cpus);
+        block_stm::rayon_info!("{}", infos.mean());
+    }
+    record(
+        name,
+        BenchmarkParameters {
+            accounts: 0,
+            txns: TXNS_NUM,
+            cpus,
+        },
+        infos,
+    );
 }
 fn conflicting_level(c: &mut Criterion) {
     #[cfg(feature = "tracing")]
@@ -34,21 +107,12 @@ fn conflicting_level(c: &mut Criterion) {
     let mut infos = BenchmarkInfos::default();
     // accounts num bigger,conflicting level lower
     for accounts_num in [3, 10, 100, 1000] {
-        let (txns, ledger) = generate_txns_and_ledger(accounts_num, 1_000_000, TXNS_NUM, 1, 1000);
+        let (txns, ledger) =
+            generate_txns_and_ledger(accounts_num, 1_000_000, TXNS_NUM, 1, 1000, SEED);
         group.bench_with_input(
             BenchmarkId::new("simulated sequential execute", accounts_num),
             &accounts_num,
-            |b, _| {
-                b.iter_custom(|iters| {
-                    let mut total = Duration::ZERO;
-                    for _ in 0..iters {
-                        let (_, info) = sequential_execute(&txns, &ledger);
-                        total += info.total_time;
-                        infos.add_info(info);
-                    }
-                    total
-                })
-            },
+            bench_fn(&mut infos, || sequential_execute(&txns, &ledger)),
         );
         log_benchmark_info(
             "simulated sequential execute",
@@ -60,17 +124,9 @@ fn conflicting_level(c: &mut Criterion) {
         group.bench_with_input(
             BenchmarkId::new("simulated aptos parallel execute", accounts_num),
             &accounts_num,
-            |b, _| {
-                b.iter_custom(|iters| {
-                    let mut total = Duration::ZERO;
-                    for _ in 0..iters {
-                        let (_, info) = aptos_parallel_execute(&txns, &ledger, num_cpus::get());
-                        total += info.total_time;
-                        infos.add_info(info);
-                    }
-                    total
-                })
-            },
+            bench_fn(&mut infos, || {
+                aptos_parallel_execute(&txns, &ledger, num_cpus::get())
+            }),
         );
         log_benchmark_info(
             "simulated aptos parallel execute",
@@ -82,17 +138,9 @@ fn conflicting_level(c: &mut Criterion) {
         group.bench_with_input(
             BenchmarkId::new("simulated my parallel execute", accounts_num),
             &accounts_num,
-            |b, _| {
-                b.iter_custom(|iters| {
-                    let mut total = Duration::ZERO;
-                    for _ in 0..iters {
-                        let (_, info) = my_parallel_execute(&txns, &ledger, num_cpus::get());
-                        total += info.total_time;
-                        infos.add_info(info);
-                    }
-                    total
-                })
-            },
+            bench_fn(&mut infos, || {
+                my_parallel_execute(&txns, &ledger, num_cpus::get())
+            }),
         );
         log_benchmark_info(
             "simulated my parallel execute",
@@ -101,6 +149,35 @@ fn conflicting_level(c: &mut Criterion) {
             num_cpus::get(),
             &mut infos,
         );
+        // concurrency learned across iterations,which falls back to sequential under high contention
+        let mut adaptive = AdaptiveConcurrency::new(num_cpus::get());
+        group.bench_with_input(
+            BenchmarkId::new("simulated my adaptive parallel execute", accounts_num),
+            &accounts_num,
+            bench_fn(&mut infos, || {
+                my_parallel_execute_adaptive(&txns, &ledger, &mut adaptive)
+            }),
+        );
+        log_benchmark_info(
+            "simulated my adaptive parallel execute",
+            accounts_num,
+            TXNS_NUM,
+            num_cpus::get(),
+            &mut infos,
+        );
+        // compare aborts of reordered blocks with the original one
+        for strategy in [ReorderStrategy::Spread, ReorderStrategy::Cluster] {
+            let (txns, _) = reorder(txns.clone(), TransferTransaction::key_set, strategy);
+            let name = format!("simulated my parallel execute ({:?})", strategy);
+            group.bench_with_input(
+                BenchmarkId::new(&name, accounts_num),
+                &accounts_num,
+                bench_fn(&mut infos, || {
+                    my_parallel_execute(&txns, &ledger, num_cpus::get())
+                }),
+            );
+            log_benchmark_info(&name, accounts_num, TXNS_NUM, num_cpus::get(), &mut infos);
+        }
     }
     group.finish();
 }
@@ -113,21 +190,14 @@ fn concurrency_level(c: &mut Criterion) {
     static ACCOUNTS_NUM: usize = 1_000;
     let mut infos = BenchmarkInfos::default();
     for concurrency_level in 2..=num_cpus::get() {
-        let (txns, ledger) = generate_txns_and_ledger(ACCOUNTS_NUM, 1_000_000, TXNS_NUM, 1, 1000);
+        let (txns, ledger) =
+            generate_txns_and_ledger(ACCOUNTS_NUM, 1_000_000, TXNS_NUM, 1, 1000, SEED);
         group.bench_with_input(
             BenchmarkId::new("simulated aptos parallel execute", concurrency_level),
             &concurrency_level,
-            |b, _| {
-                b.iter_custom(|iters| {
-                    let mut total = Duration::ZERO;
-                    for _ in 0..iters {
-                        let (_, info) = aptos_parallel_execute(&txns, &ledger, concurrency_level);
-                        total += info.total_time;
-                        infos.add_info(info);
-                    }
-                    total
-                })
-            },
+            bench_fn(&mut infos, || {
+                aptos_parallel_execute(&txns, &ledger, concurrency_level)
+            }),
         );
         log_benchmark_info(
             "simulated aptos parallel execute",
@@ -139,17 +209,9 @@ fn concurrency_level(c: &mut Criterion) {
         group.bench_with_input(
             BenchmarkId::new("simulated my parallel execute", concurrency_level),
             &concurrency_level,
-            |b, _| {
-                b.iter_custom(|iters| {
-                    let mut total = Duration::ZERO;
-                    for _ in 0..iters {
-                        let (_, info) = my_parallel_execute(&txns, &ledger, concurrency_level);
-                        total += info.total_time;
-                        infos.add_info(info);
-                    }
-                    total
-                })
-            },
+            bench_fn(&mut infos, || {
+                my_parallel_execute(&txns, &ledger, concurrency_level)
+            }),
         );
         log_benchmark_info(
             "simulated my parallel execute",
@@ -161,8 +223,273 @@ fn concurrency_level(c: &mut Criterion) {
     }
     group.finish();
 }
+fn distribution(c: &mut Criterion) {
+    #[cfg(feature = "tracing")]
+    let _ = install_logger();
+    let mut group = c.benchmark_group("distribution");
+    group.throughput(Throughput::Elements(TXNS_NUM as u64));
+    static ACCOUNTS_NUM: usize = 1_000;
+    let mut infos = BenchmarkInfos::default();
+    // contention curves of skewed workloads
+    let distributions = [
+        ("uniform", AccountDistribution::Uniform),
+        (
+            "zipfian 0.5",
+            AccountDistribution::Zipfian { exponent: 0.5 },
+        ),
+        (
+            "zipfian 1.0",
+            AccountDistribution::Zipfian { exponent: 1.0 },
+        ),
+        (
+            "zipfian 1.5",
+            AccountDistribution::Zipfian { exponent: 1.5 },
+        ),
+        (
+            "hotspot 90/10",
+            AccountDistribution::Hotspot {
+                traffic: 0.9,
+                keys: 0.1,
+            },
+        ),
+        (
+            "hotspot 99/1",
+            AccountDistribution::Hotspot {
+                traffic: 0.99,
+                keys: 0.01,
+            },
+        ),
+        ("sink", AccountDistribution::Sink),
+    ];
+    for (parameter, distribution) in distributions {
+        let (txns, ledger) = TransferGenerator::new(ACCOUNTS_NUM, 1_000_000)
+            .with_distribution(distribution)
+            .with_seed(SEED)
+            .generate(TXNS_NUM);
+        group.bench_with_input(
+            BenchmarkId::new("simulated sequential execute", parameter),
+            &parameter,
+            bench_fn(&mut infos, || sequential_execute(&txns, &ledger)),
+        );
+        log_benchmark_info(
+            &format!("simulated sequential execute ({})", parameter),
+            ACCOUNTS_NUM,
+            TXNS_NUM,
+            1,
+            &mut infos,
+        );
+        group.bench_with_input(
+            BenchmarkId::new("simulated my parallel execute", parameter),
+            &parameter,
+            bench_fn(&mut infos, || {
+                my_parallel_execute(&txns, &ledger, num_cpus::get())
+            }),
+        );
+        log_benchmark_info(
+            &format!("simulated my parallel execute ({})", parameter),
+            ACCOUNTS_NUM,
+            TXNS_NUM,
+            num_cpus::get(),
+            &mut infos,
+        );
+        // explain contention curve by the hottest keys
+        let contention = my_parallel_execute_contention(&txns, &ledger, num_cpus::get());
+        block_stm::rayon_info!("contention ({}):\n{}", parameter, contention.top(5));
+    }
+    group.finish();
+}
+fn bench_workload<W: Workload>(
+    group: &mut BenchmarkGroup<WallTime>,
+    infos: &mut BenchmarkInfos,
+    workload: W,
+) {
+    let name = workload.name();
+    let (txns, state) = workload.generate(TXNS_NUM, SEED);
+    group.bench_with_input(
+        BenchmarkId::new("simulated sequential execute", &name),
+        &name,
+        bench_fn(infos, || sequential_execute_workload::<W>(&txns, &state)),
+    );
+    log_workload_info(
+        &format!("simulated sequential execute ({})", name),
+        1,
+        infos,
+    );
+    group.bench_with_input(
+        BenchmarkId::new("simulated my parallel execute", &name),
+        &name,
+        bench_fn(infos, || {
+            parallel_execute_workload::<W>(&txns, &state, num_cpus::get())
+        }),
+    );
+    log_workload_info(
+        &format!("simulated my parallel execute ({})", name),
+        num_cpus::get(),
+        infos,
+    );
+}
+fn workload(c: &mut Criterion) {
+    #[cfg(feature = "tracing")]
+    let _ = install_logger();
+    let mut group = c.benchmark_group("workload");
+    group.throughput(Throughput::Elements(TXNS_NUM as u64));
+    let mut infos = BenchmarkInfos::default();
+    // read/write shapes of transaction families
+    for pools in [1, 10, 100] {
+        bench_workload(
+            &mut group,
+            &mut infos,
+            AmmWorkload {
+                pools,
+                init_reserve: 1_000_000_000,
+            },
+        );
+    }
+    bench_workload(&mut group, &mut infos, NftWorkload { owners: 1_000 });
+    for accounts in [10, 1_000] {
+        bench_workload(
+            &mut group,
+            &mut infos,
+            NonceWorkload {
+                accounts,
+                stale_ratio: 0.1,
+            },
+        );
+    }
+    for update_ratio in [0.0, 0.01, 0.1] {
+        bench_workload(
+            &mut group,
+            &mut infos,
+            OracleWorkload {
+                feeds: 10,
+                accounts: 1_000,
+                feeds_per_lookup: 3,
+                update_ratio,
+            },
+        );
+    }
+    group.finish();
+}
+fn cost_model(c: &mut Criterion) {
+    #[cfg(feature = "tracing")]
+    let _ = install_logger();
+    let mut group = c.benchmark_group("cost_model");
+    group.throughput(Throughput::Elements(TXNS_NUM as u64));
+    static ACCOUNTS_NUM: usize = 10;
+    let mut infos = BenchmarkInfos::default();
+    // wasted re-executions occupy cpu,so throughput depends on cost model under contention
+    let models = [
+        ("fixed", CostModel::Fixed(Duration::from_micros(100))),
+        (
+            "uniform",
+            CostModel::Uniform {
+                min: Duration::from_micros(10),
+                max: Duration::from_micros(190),
+            },
+        ),
+        (
+            "per access",
+            CostModel::PerAccess {
+                base: Duration::from_micros(20),
+                per_read: Duration::from_micros(20),
+                per_write: Duration::from_micros(20),
+            },
+        ),
+    ];
+    let (txns, ledger) = TransferGenerator::new(ACCOUNTS_NUM, 1_000_000)
+        .with_seed(SEED)
+        .generate(TXNS_NUM);
+    for (parameter, model) in models {
+        set_cost_model(model);
+        group.bench_with_input(
+            BenchmarkId::new("simulated sequential execute", parameter),
+            &parameter,
+            bench_fn(&mut infos, || sequential_execute(&txns, &ledger)),
+        );
+        log_benchmark_info(
+            &format!("simulated sequential execute ({})", parameter),
+            ACCOUNTS_NUM,
+            TXNS_NUM,
+            1,
+            &mut infos,
+        );
+        group.bench_with_input(
+            BenchmarkId::new("simulated my parallel execute", parameter),
+            &parameter,
+            bench_fn(&mut infos, || {
+                my_parallel_execute(&txns, &ledger, num_cpus::get())
+            }),
+        );
+        log_benchmark_info(
+            &format!("simulated my parallel execute ({})", parameter),
+            ACCOUNTS_NUM,
+            TXNS_NUM,
+            num_cpus::get(),
+            &mut infos,
+        );
+        // explain contention curve by the hottest keys
+        let contention = my_parallel_execute_contention(&txns, &ledger, num_cpus::get());
+        block_stm::rayon_info!("contention ({}):\n{}", parameter, contention.top(5));
+    }
+    set_cost_model(CostModel::default());
+    group.finish();
+}
+fn mvstore(c: &mut Criterion) {
+    #[cfg(feature = "tracing")]
+    let _ = install_logger();
+    let mut group = c.benchmark_group("mvstore");
+    group.throughput(Throughput::Elements(TXNS_NUM as u64));
+    let mut infos = BenchmarkInfos::default();
+    for accounts_num in [3, 10, 100, 1000] {
+        let (txns, ledger) = TransferGenerator::new(accounts_num, 1_000_000)
+            .with_money(1, 1000)
+            .with_seed(SEED)
+            .generate(TXNS_NUM);
+        group.bench_with_input(
+            BenchmarkId::new("simulated my parallel execute (MVMap)", accounts_num),
+            &accounts_num,
+            bench_fn(&mut infos, || {
+                my_parallel_execute_with_store(
+                    &txns,
+                    &ledger,
+                    num_cpus::get(),
+                    MVMapConfig::default(),
+                )
+            }),
+        );
+        log_benchmark_info(
+            "simulated my parallel execute (MVMap)",
+            accounts_num,
+            TXNS_NUM,
+            num_cpus::get(),
+            &mut infos,
+        );
+        group.bench_with_input(
+            BenchmarkId::new("simulated my parallel execute (DenseMVMap)", accounts_num),
+            &accounts_num,
+            bench_fn(&mut infos, || {
+                my_parallel_execute_with_store(
+                    &txns,
+                    &ledger,
+                    num_cpus::get(),
+                    DenseMVMapConfig {
+                        capacity: accounts_num,
+                    },
+                )
+            }),
+        );
+        log_benchmark_info(
+            "simulated my parallel execute (DenseMVMap)",
+            accounts_num,
+            TXNS_NUM,
+            num_cpus::get(),
+            &mut infos,
+        );
+    }
+    group.finish();
+}
 criterion_group!(
     name = benches;
     config=Criterion::default().with_profiler(PProfProfiler::new(100,Output::Flamegraph(None))).sample_size(10);
-    targets=conflicting_level, concurrency_level);
+    targets=conflicting_level, concurrency_level, distribution, workload, cost_model, mvstore);
 criterion_main!(benches);
@@ -0,0 +1,250 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use crate::types::AtomicUsize;
+
+/// executions between two adjustments of active workers
+const WINDOW: usize = 64;
+/// shrink active workers by half if aborts per execution of window is above it
+const SHRINK_RATE: f64 = 0.3;
+/// grow active workers by one if aborts per execution of window is below it
+const GROW_RATE: f64 = 0.1;
+/// weight of the latest block in moving average of cost
+const ALPHA: f64 = 0.3;
+/// blocks between two probes of neighbouring levels of the best one
+const PROBE_PERIOD: usize = 16;
+
+/// limit of active workers of a running block,adjusted by abort rate.
+///
+/// workers above the limit park between tasks (never while holding one),worker 0 is always active,
+/// which makes execution effectively sequential under heavy contention.
+/// workers blocked on dependencies don't count,so that another worker can resume them.
+pub(crate) struct Throttle {
+    max: usize,
+    limit: AtomicUsize,
+    executions: AtomicUsize,
+    aborts: AtomicUsize,
+}
+impl Throttle {
+    /// start with `limit` active workers of `max` spawned ones
+    pub fn new(limit: usize, max: usize) -> Self {
+        Self {
+            max,
+            limit: AtomicUsize::new(limit.clamp(1, max)),
+            executions: AtomicUsize::new(0),
+            aborts: AtomicUsize::new(0),
+        }
+    }
+    /// current limit of active workers
+    pub fn limit(&self) -> usize {
+        self.limit.load()
+    }
+    /// whether worker may take next task,given number of blocked workers
+    pub fn admits(&self, worker_idx: usize, blocked: usize) -> bool {
+        worker_idx < self.limit.load() + blocked
+    }
+    /// count aborted incarnation
+    pub fn record_abort(&self) {
+        self.aborts.increment();
+    }
+    /// count execution,the last execution of window adjusts limit,return whether limit is changed
+    pub fn record_execution(&self) -> bool {
+        let mut executions = self.executions.load();
+        // exactly one execution closes each window,even if others are counted concurrently
+        loop {
+            let next = if executions + 1 < WINDOW {
+                executions + 1
+            } else {
+                0
+            };
+            match self.executions.compare_exchange_weak(
+                executions,
+                next,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => break,
+                Err(current) => executions = current,
+            }
+        }
+        if executions + 1 < WINDOW {
+            return false;
+        }
+        let rate = self.aborts.swap(0, Ordering::SeqCst) as f64 / WINDOW as f64;
+        let limit = self.limit.load();
+        let next = if rate > SHRINK_RATE {
+            (limit / 2).max(1)
+        } else if rate < GROW_RATE {
+            (limit + 1).min(self.max)
+        } else {
+            limit
+        };
+        self.limit.store(next, Ordering::SeqCst);
+        next != limit
+    }
+}
+/// adaptive concurrency shared by consecutive blocks,see `ParallelExecutor::execute_transactions_adaptive`.
+///
+/// it keeps a moving average of execution time per transaction for each starting concurrency level,
+/// and starts next block with the best one,trying levels suggested by abort rate (or neighbours of the best) from time to time,
+/// so it follows the recent workload.
+#[derive(Debug, Clone)]
+pub struct AdaptiveConcurrency {
+    max: usize,
+    /// moving average of seconds per transaction,indexed by concurrency level - 1
+    costs: Vec<Option<f64>>,
+    next: usize,
+    /// active workers which the last block settled at
+    settled: Option<usize>,
+    blocks: usize,
+}
+impl AdaptiveConcurrency {
+    /// adaptive concurrency between 1 and `max_concurrency_level`,starting optimistically from the highest one
+    pub fn new(max_concurrency_level: usize) -> Self {
+        assert!(max_concurrency_level > 0, "at least one worker");
+        Self {
+            max: max_concurrency_level,
+            costs: vec![None; max_concurrency_level],
+            next: max_concurrency_level,
+            settled: None,
+            blocks: 0,
+        }
+    }
+    /// concurrency level which next block starts with
+    pub fn concurrency_level(&self) -> usize {
+        self.next
+    }
+    /// active workers which the last block settled at after throttling,`None` if no block is observed
+    pub fn settled(&self) -> Option<usize> {
+        self.settled
+    }
+    /// best concurrency level of recent blocks,`None` if no block is observed
+    pub fn best(&self) -> Option<usize> {
+        self.costs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, cost)| cost.map(|cost| (i + 1, cost)))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("cost is not nan"))
+            .map(|(level, _)| level)
+    }
+    /// observe block started with `level`,whose active workers settled at `settled`
+    pub(crate) fn observe(&mut self, level: usize, settled: usize, txns: usize, time: Duration) {
+        if txns == 0 {
+            return;
+        }
+        self.blocks += 1;
+        self.settled = Some(settled);
+        let cost = time.as_secs_f64() / txns as f64;
+        let average = &mut self.costs[level - 1];
+        *average = Some(average.map_or(cost, |average| ALPHA * cost + (1.0 - ALPHA) * average));
+        let best = self.best().expect("block is observed");
+        let unexplored =
+            |level: usize| (1..=self.max).contains(&level) && self.costs[level - 1].is_none();
+        self.next = if settled < level && unexplored(settled) {
+            // abort rate suggests fewer workers
+            settled
+        } else if unexplored(best + 1) {
+            best + 1
+        } else if best > 1 && unexplored(best - 1) {
+            best - 1
+        } else if self.blocks % PROBE_PERIOD == 0 {
+            // workload may have changed
+            let neighbour = if self.blocks / PROBE_PERIOD % 2 == 0 {
+                best + 1
+            } else {
+                best.saturating_sub(1)
+            };
+            neighbour.clamp(1, self.max)
+        } else {
+            best
+        };
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{AdaptiveConcurrency, Throttle, WINDOW};
+    use crate::{
+        scheduler::Scheduler,
+        types::{AtomicUsize, Condvar},
+    };
+
+    #[test]
+    fn throttle() {
+        let throttle = Throttle::new(8, 8);
+        for _ in 0..WINDOW {
+            throttle.record_abort();
+            throttle.record_execution();
+        }
+        assert_eq!(throttle.limit(), 4);
+        assert!(throttle.admits(3, 0) && !throttle.admits(4, 0));
+        assert!(throttle.admits(4, 1));
+        for _ in 0..WINDOW {
+            throttle.record_execution();
+        }
+        assert_eq!(throttle.limit(), 5);
+    }
+    #[test]
+    fn concurrent_windows() {
+        let throttle = Throttle::new(1, 64);
+        let changes = AtomicUsize::new(0);
+        crossbeam::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|_| {
+                    for _ in 0..WINDOW * 4 {
+                        if throttle.record_execution() {
+                            changes.increment();
+                        }
+                    }
+                });
+            }
+        })
+        .unwrap();
+        // no abort,every window grows limit by one
+        assert_eq!(changes.load(), 32);
+        assert_eq!(throttle.limit(), 33);
+    }
+    #[test]
+    fn park() {
+        let scheduler = Scheduler::new(1);
+        let throttle = Throttle::new(1, 3);
+        crossbeam::scope(|s| {
+            // woken up when limit grows
+            let parked = s.spawn(|_| scheduler.park(|blocked| throttle.admits(1, blocked)));
+            for _ in 0..WINDOW {
+                if throttle.record_execution() {
+                    scheduler.unpark();
+                }
+            }
+            assert!(parked.join().unwrap());
+            // woken up when a worker blocks on dependency
+            let parked = s.spawn(|_| scheduler.park(|blocked| throttle.admits(2, blocked)));
+            let condvar = Condvar::new();
+            let blocked = condvar.clone();
+            s.spawn(|_| scheduler.wait(blocked));
+            assert!(parked.join().unwrap());
+            condvar.notify_one();
+        })
+        .unwrap();
+        // woken up when scheduler is done
+        scheduler.halt();
+        assert!(!scheduler.park(|_| false));
+    }
+    #[test]
+    fn learn() {
+        let mut adaptive = AdaptiveConcurrency::new(4);
+        assert_eq!(adaptive.concurrency_level(), 4);
+        // contended workload,throttle settles at 1 and lower levels are faster
+        let time = |level: usize| Duration::from_millis(10 * level as u64);
+        adaptive.observe(4, 1, 100, time(4));
+        assert_eq!(adaptive.concurrency_level(), 1);
+        adaptive.observe(1, 1, 100, time(1));
+        assert_eq!(adaptive.concurrency_level(), 2);
+        adaptive.observe(2, 2, 100, time(2));
+        for _ in 0..10 {
+            let level = adaptive.concurrency_level();
+            adaptive.observe(level, level, 100, time(level));
+        }
+        assert_eq!(adaptive.best(), Some(1));
+    }
+}
@@ -0,0 +1,213 @@
+//! command-line runner of blocks described in files
+//!
+//! usage: `block_stm --genesis <file> --block <file> [--family simulated] [--mode sequential|parallel|both]
+//! [--concurrency <n>] [--output <dir>]`
+//!
+//! files with `.json` extension are (de)serialized as json,others as bincode,
+//! output directory receives final state,per-transaction outputs and `stats.json`,
+//! outputs are taken from sequential run if any,else from the executed block of parallel run.
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context};
+use block_stm::test_utils::{
+    simulated::{
+        my_parallel_execute_with_outputs, sequential_execute, Ledger, TransferTransaction,
+    },
+    BenchmarkInfo,
+};
+use either::Either;
+use serde::{de::DeserializeOwned, Serialize};
+
+const USAGE: &str = "usage: block_stm --genesis <file> --block <file> [--family simulated] \
+[--mode sequential|parallel|both] [--concurrency <n>] [--output <dir>]";
+
+/// transaction family of block
+enum Family {
+    /// `TransferTransaction` on `Ledger`
+    Simulated,
+}
+/// which executors to run
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Sequential,
+    Parallel,
+    Both,
+}
+struct Args {
+    genesis: PathBuf,
+    block: PathBuf,
+    family: Family,
+    mode: Mode,
+    concurrency_level: usize,
+    output: PathBuf,
+}
+impl Args {
+    fn parse() -> anyhow::Result<Self> {
+        let mut genesis = None;
+        let mut block = None;
+        let mut family = Family::Simulated;
+        let mut mode = Mode::Both;
+        let mut concurrency_level = num_cpus::get();
+        let mut output = PathBuf::from(".");
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("missing value of {}\n{}", flag, USAGE))
+            };
+            match flag.as_str() {
+                "--genesis" => genesis = Some(PathBuf::from(value()?)),
+                "--block" => block = Some(PathBuf::from(value()?)),
+                "--family" => {
+                    family = match value()?.as_str() {
+                        "simulated" => Family::Simulated,
+                        other => bail!("unknown family {}", other),
+                    }
+                }
+                "--mode" => {
+                    mode = match value()?.as_str() {
+                        "sequential" => Mode::Sequential,
+                        "parallel" => Mode::Parallel,
+                        "both" => Mode::Both,
+                        other => bail!("unknown mode {}", other),
+                    }
+                }
+                "--concurrency" => {
+                    concurrency_level = value()?.parse().context("invalid concurrency")?;
+                    if concurrency_level == 0 || concurrency_level > num_cpus::get() {
+                        bail!(
+                            "concurrency should be in 1..={},got {}\n{}",
+                            num_cpus::get(),
+                            concurrency_level,
+                            USAGE
+                        );
+                    }
+                }
+                "--output" => output = PathBuf::from(value()?),
+                _ => bail!("unknown argument {}\n{}", flag, USAGE),
+            }
+        }
+        Ok(Self {
+            genesis: genesis.ok_or_else(|| anyhow!("missing --genesis\n{}", USAGE))?,
+            block: block.ok_or_else(|| anyhow!("missing --block\n{}", USAGE))?,
+            family,
+            mode,
+            concurrency_level,
+            output,
+        })
+    }
+}
+/// timing stats of run,written as `stats.json`
+#[derive(Serialize)]
+struct Stats {
+    txns: usize,
+    concurrency_level: usize,
+    sequential_ms: Option<f64>,
+    parallel_ms: Option<f64>,
+    execute_ms: Option<f64>,
+    collect_ms: Option<f64>,
+    num_aborts: Option<usize>,
+    speedup: Option<f64>,
+    /// whether final states and per-transaction outputs of sequential and parallel runs are equal
+    consistent: Option<bool>,
+}
+fn is_json(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == "json")
+}
+fn load<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let reader = BufReader::new(
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?,
+    );
+    let t = if is_json(path) {
+        serde_json::from_reader(reader)?
+    } else {
+        bincode::deserialize_from(reader)?
+    };
+    Ok(t)
+}
+/// store as the format of `like`
+fn store<T: Serialize>(dir: &Path, name: &str, like: &Path, t: &T) -> anyhow::Result<()> {
+    let path = dir.join(if is_json(like) {
+        format!("{}.json", name)
+    } else {
+        format!("{}.bin", name)
+    });
+    let writer = BufWriter::new(
+        File::create(&path).with_context(|| format!("failed to create {}", path.display()))?,
+    );
+    if is_json(like) {
+        serde_json::to_writer_pretty(writer, t)?;
+    } else {
+        bincode::serialize_into(writer, t)?;
+    }
+    Ok(())
+}
+fn millis(info: &BenchmarkInfo) -> f64 {
+    info.total_time.as_secs_f64() * 1000.0
+}
+fn run_simulated(args: &Args) -> anyhow::Result<Stats> {
+    let ledger: Ledger = load(&args.genesis)?;
+    let txns: Vec<TransferTransaction> = load(&args.block)?;
+    let sequential = if args.mode != Mode::Parallel {
+        let (outputs, info) = sequential_execute(&txns, &ledger);
+        Some((
+            ledger.clone().apply(Either::Left(outputs.clone())),
+            outputs,
+            info,
+        ))
+    } else {
+        None
+    };
+    let parallel = if args.mode != Mode::Sequential {
+        let (outputs, output, info) =
+            my_parallel_execute_with_outputs(&txns, &ledger, args.concurrency_level);
+        Some((ledger.clone().apply(Either::Right(output)), outputs, info))
+    } else {
+        None
+    };
+    let (state, outputs) = match (&sequential, &parallel) {
+        (Some((state, outputs, _)), _) | (None, Some((state, outputs, _))) => (state, outputs),
+        (None, None) => unreachable!(),
+    };
+    store(&args.output, "state", &args.genesis, state)?;
+    store(&args.output, "outputs", &args.block, outputs)?;
+    let s_info = sequential.as_ref().map(|(_, _, info)| info);
+    let p_info = parallel.as_ref().map(|(_, _, info)| info);
+    Ok(Stats {
+        txns: txns.len(),
+        concurrency_level: args.concurrency_level,
+        sequential_ms: s_info.map(millis),
+        parallel_ms: p_info.map(millis),
+        execute_ms: p_info
+            .and_then(|info| info.execute_time)
+            .map(|t| t.as_secs_f64() * 1000.0),
+        collect_ms: p_info
+            .and_then(|info| info.collect_time)
+            .map(|t| t.as_secs_f64() * 1000.0),
+        num_aborts: p_info.and_then(|info| info.num_aborts),
+        speedup: s_info
+            .zip(p_info)
+            .map(|(s, p)| s.total_time.as_secs_f64() / p.total_time.as_secs_f64()),
+        consistent: sequential
+            .as_ref()
+            .zip(parallel.as_ref())
+            .map(|(s, p)| s.0 == p.0 && s.1 == p.1),
+    })
+}
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse()?;
+    fs::create_dir_all(&args.output)
+        .with_context(|| format!("failed to create {}", args.output.display()))?;
+    let stats = match args.family {
+        Family::Simulated => run_simulated(&args)?,
+    };
+    serde_json::to_writer_pretty(File::create(args.output.join("stats.json"))?, &stats)?;
+    if stats.consistent == Some(false) {
+        bail!("sequential and parallel states differ");
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! worker process of multi-process execution,which hosts the simulated transfer vm
+//!
+//! usage: `block_stm_worker <accounts_num> <init_balance> [crash_after]`,genesis ledger is generated by `generate_ledger`,
+//! worker exits abruptly after reading `crash_after` bytes from coordinator,which is used to test respawn of workers.
+use std::io::{self, Read};
+
+use block_stm::test_utils::simulated::{generate_ledger, my_worker_serve};
+
+/// stdin which exits process once its budget of bytes is read
+struct CrashingStdin(usize);
+impl Read for CrashingStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0 == 0 {
+            std::process::exit(1);
+        }
+        let len = buf.len().min(self.0);
+        let read = io::stdin().read(&mut buf[..len])?;
+        self.0 -= read;
+        Ok(read)
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<usize> = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("argument should be a number"))
+        .collect();
+    match args[..] {
+        [accounts_num, init_balance] => {
+            my_worker_serve(&generate_ledger(accounts_num, init_balance), io::stdin())
+        }
+        [accounts_num, init_balance, crash_after] => my_worker_serve(
+            &generate_ledger(accounts_num, init_balance),
+            CrashingStdin(crash_after),
+        ),
+        _ => panic!("usage: block_stm_worker <accounts_num> <init_balance> [crash_after]"),
+    }
+}
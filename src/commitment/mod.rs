@@ -0,0 +1,176 @@
+use std::fmt::Debug;
+
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::core::{ValueBytes, WireBytes};
+
+/// sha256 over concatenation of parts
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// hasher used by commitment,leaves of merkle tree are `(key digest, value bytes)`
+///
+/// `Send + Sync` needed by rayon
+pub trait CommitmentHasher<K>: Send + Sync {
+    /// digest type,`Ord` needed to order leaves by key digest
+    type Digest: Clone + Ord + Debug + Send + Sync;
+    /// digest of key
+    fn hash_key(&self, key: &K) -> Self::Digest;
+    /// digest of leaf,`None` indicates deletion
+    fn hash_leaf(&self, key: &Self::Digest, value: Option<&[u8]>) -> Self::Digest;
+    /// digest of inner node
+    fn hash_node(&self, left: &Self::Digest, right: &Self::Digest) -> Self::Digest;
+    /// digest of empty tree
+    fn empty(&self) -> Self::Digest;
+}
+/// SHA-256 hasher over wire bytes of keys,each kind of digest is prefixed by a domain byte,
+/// so digests are the same on any toolchain or platform.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+impl<K: WireBytes> CommitmentHasher<K> for Sha256Hasher {
+    type Digest = [u8; 32];
+
+    fn hash_key(&self, key: &K) -> [u8; 32] {
+        sha256(&[&[0], &key.to_bytes()])
+    }
+    fn hash_leaf(&self, key: &[u8; 32], value: Option<&[u8]>) -> [u8; 32] {
+        match value {
+            Some(value) => sha256(&[&[1], key, &[1], value]),
+            None => sha256(&[&[1], key, &[0]]),
+        }
+    }
+    fn hash_node(&self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        sha256(&[&[2], left, right])
+    }
+    fn empty(&self) -> [u8; 32] {
+        sha256(&[&[3]])
+    }
+}
+/// commitment to output of block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commitment<D> {
+    /// merkle root over changed keys of block
+    pub state_root: D,
+    /// accumulated write set hashes,`accumulator[i]` commits to write sets of transactions `0..=i`
+    pub accumulator: Vec<D>,
+}
+/// binary merkle root over `(key,value)` pairs,computed in parallel.
+///
+/// leaves are ordered by key digest then key bytes (in case digests collide),
+/// so root doesn't depend on order of pairs,keys must be unique.
+pub fn merkle_root<K, V, H>(hasher: &H, pairs: &[(K, Option<V>)]) -> H::Digest
+where
+    K: WireBytes + Sync,
+    V: ValueBytes + Sync,
+    H: CommitmentHasher<K>,
+{
+    let mut leaves: Vec<_> = pairs
+        .par_iter()
+        .map(|(key, value)| {
+            (
+                hasher.hash_key(key),
+                key.to_bytes(),
+                value.as_ref().map(|v| v.serialize()),
+            )
+        })
+        .collect();
+    leaves.par_sort_unstable_by(|(a, a_bytes, _), (b, b_bytes, _)| {
+        a.cmp(b).then_with(|| a_bytes.cmp(b_bytes))
+    });
+    let mut level: Vec<_> = leaves
+        .par_iter()
+        .map(|(key, _, value)| hasher.hash_leaf(key, value.as_deref()))
+        .collect();
+    if level.is_empty() {
+        return hasher.empty();
+    }
+    while level.len() > 1 {
+        // odd node is promoted to next level
+        level = level
+            .par_chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hasher.hash_node(left, right),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level.pop().expect("level is not empty")
+}
+/// accumulate write sets of transactions in order,return accumulator after each transaction.
+///
+/// write set hashes are computed in parallel,`accumulator[i] = hash_node(accumulator[i - 1], merkle_root(write_sets[i]))`.
+pub fn accumulate<K, V, H>(hasher: &H, write_sets: &[Vec<(K, Option<V>)>]) -> Vec<H::Digest>
+where
+    K: WireBytes + Sync,
+    V: ValueBytes + Sync,
+    H: CommitmentHasher<K>,
+{
+    let write_set_hashes: Vec<_> = write_sets
+        .par_iter()
+        .map(|write_set| merkle_root(hasher, write_set))
+        .collect();
+    write_set_hashes
+        .iter()
+        .scan(hasher.empty(), |accumulator, hash| {
+            *accumulator = hasher.hash_node(accumulator, hash);
+            Some(accumulator.clone())
+        })
+        .collect()
+}
+#[cfg(test)]
+mod tests {
+    use super::{merkle_root, sha256, CommitmentHasher, Sha256Hasher};
+
+    /// every key collides,leaves are ordered by key bytes only
+    struct CollidingHasher;
+    impl CommitmentHasher<usize> for CollidingHasher {
+        type Digest = [u8; 32];
+
+        fn hash_key(&self, _: &usize) -> [u8; 32] {
+            [0; 32]
+        }
+        fn hash_leaf(&self, key: &[u8; 32], value: Option<&[u8]>) -> [u8; 32] {
+            CommitmentHasher::<usize>::hash_leaf(&Sha256Hasher, key, value)
+        }
+        fn hash_node(&self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+            CommitmentHasher::<usize>::hash_node(&Sha256Hasher, left, right)
+        }
+        fn empty(&self) -> [u8; 32] {
+            CommitmentHasher::<usize>::empty(&Sha256Hasher)
+        }
+    }
+    #[test]
+    fn sha256_of_parts() {
+        let hex =
+            |digest: [u8; 32]| -> String { digest.iter().map(|b| format!("{:02x}", b)).collect() };
+        assert_eq!(
+            hex(sha256(&[])),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(sha256(&[b"a", b"bc"])),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+    #[test]
+    fn root_is_independent_of_order() {
+        let pairs = vec![(0usize, Some(10usize)), (1, None), (2, Some(30))];
+        let mut reversed = pairs.clone();
+        reversed.reverse();
+        assert_eq!(
+            merkle_root(&Sha256Hasher, &pairs),
+            merkle_root(&Sha256Hasher, &reversed)
+        );
+        assert_eq!(
+            merkle_root(&CollidingHasher, &pairs),
+            merkle_root(&CollidingHasher, &reversed)
+        );
+    }
+}
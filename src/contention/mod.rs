@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    hash::Hash,
+};
+
+use crate::types::TxnIndex;
+
+/// contention of block,which ranks keys and writer transactions by the aborts they caused.
+///
+/// each abort is caused by a failed validation,whose first invalid read is attributed to its key
+/// and the transaction that wrote the new (or removed the old) version of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentionReport<K> {
+    /// keys with number of aborts they caused,in descending order (ties in order of first abort)
+    pub hot_keys: Vec<(K, usize)>,
+    /// writer transactions with number of aborts they caused,in descending order
+    pub hot_writers: Vec<(TxnIndex, usize)>,
+}
+impl<K: Eq + Hash + Clone> ContentionReport<K> {
+    /// rank keys and writers of aborts
+    pub(crate) fn new(aborts: &[(K, TxnIndex)]) -> Self {
+        let mut positions: HashMap<&K, usize> = HashMap::new();
+        let mut hot_keys: Vec<(K, usize)> = Vec::new();
+        let mut writers: HashMap<TxnIndex, usize> = HashMap::new();
+        for (key, writer) in aborts {
+            let position = *positions.entry(key).or_insert_with(|| {
+                hot_keys.push((key.clone(), 0));
+                hot_keys.len() - 1
+            });
+            hot_keys[position].1 += 1;
+            *writers.entry(*writer).or_default() += 1;
+        }
+        // stable,so ties keep order of first abort
+        hot_keys.sort_by(|(_, a1), (_, a2)| a2.cmp(a1));
+        let mut hot_writers: Vec<_> = writers.into_iter().collect();
+        hot_writers.sort_unstable_by(|(t1, a1), (t2, a2)| a2.cmp(a1).then_with(|| t1.cmp(t2)));
+        Self {
+            hot_keys,
+            hot_writers,
+        }
+    }
+}
+impl<K> ContentionReport<K> {
+    /// total number of attributed aborts
+    pub fn num_aborts(&self) -> usize {
+        self.hot_keys.iter().map(|(_, aborts)| aborts).sum()
+    }
+    /// keep the `n` hottest keys and writers,e.g. before printing
+    pub fn top(&self, n: usize) -> Self
+    where
+        K: Clone,
+    {
+        Self {
+            hot_keys: self.hot_keys.iter().take(n).cloned().collect(),
+            hot_writers: self.hot_writers.iter().take(n).cloned().collect(),
+        }
+    }
+}
+impl<K> Default for ContentionReport<K> {
+    fn default() -> Self {
+        Self {
+            hot_keys: Vec::new(),
+            hot_writers: Vec::new(),
+        }
+    }
+}
+impl<K: Debug> fmt::Display for ContentionReport<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "hot keys:")?;
+        for (key, aborts) in &self.hot_keys {
+            writeln!(f, "  {:?} ({} aborts)", key, aborts)?;
+        }
+        writeln!(f, "hot writers:")?;
+        for (txn_idx, aborts) in &self.hot_writers {
+            writeln!(f, "  transaction {} ({} aborts)", txn_idx, aborts)?;
+        }
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::ContentionReport;
+
+    #[test]
+    fn ranking() {
+        let aborts = [(7, 2), (3, 1), (7, 1), (7, 4), (3, 4), (5, 4)];
+        let report = ContentionReport::new(&aborts);
+        assert_eq!(report.hot_keys, vec![(7, 3), (3, 2), (5, 1)]);
+        assert_eq!(report.hot_writers, vec![(4, 3), (1, 2), (2, 1)]);
+        assert_eq!(report.num_aborts(), aborts.len());
+        assert_eq!(report.top(1).hot_writers, vec![(4, 3)]);
+        // ties keep order of first abort
+        let report = ContentionReport::new(&[(9, 0), (2, 0), (2, 1), (9, 1)]);
+        assert_eq!(report.hot_keys, vec![(9, 2), (2, 2)]);
+        assert_eq!(
+            report.top(1).to_string(),
+            "hot keys:\n  9 (2 aborts)\nhot writers:\n  transaction 0 (2 aborts)\n"
+        );
+    }
+}
@@ -3,11 +3,51 @@ use std::{fmt::Debug, hash::Hash};
 use crate::mvmemory::MVMemoryView;
 /// trait used to serialize/deserialize Value
 pub trait ValueBytes {
-    /// serialize `Self` to `Vec<u8>`,`None` indicates deletion.
-    fn serialize(&self) -> Option<Vec<u8>>;
+    /// serialize `Self` to `Vec<u8>`
+    fn serialize(&self) -> Vec<u8>;
     /// deserialize `Vec<u8>` to `Self`
     fn deserialize(bytes: &[u8]) -> Self;
 }
+impl ValueBytes for u64 {
+    fn serialize(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Self {
+        Self::from_bytes(bytes)
+    }
+}
+/// trait used to transfer keys and transactions between processes,or persist them to storage
+pub trait WireBytes: Sized {
+    /// serialize `Self` to `Vec<u8>`
+    fn to_bytes(&self) -> Vec<u8>;
+    /// deserialize `Vec<u8>` to `Self`
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+impl WireBytes for usize {
+    fn to_bytes(&self) -> Vec<u8> {
+        (*self as u64).to_bytes()
+    }
+    fn from_bytes(bytes: &[u8]) -> Self {
+        u64::from_bytes(bytes) as usize
+    }
+}
+impl WireBytes for u64 {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+    fn from_bytes(bytes: &[u8]) -> Self {
+        u64::from_le_bytes(bytes.try_into().expect("invalid u64 bytes"))
+    }
+}
+impl WireBytes for u32 {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+    fn from_bytes(bytes: &[u8]) -> Self {
+        u32::from_le_bytes(bytes.try_into().expect("invalid u32 bytes"))
+    }
+}
 /// transaction type
 ///
 /// `Sync` needed by rayon
@@ -25,22 +65,76 @@ pub trait Transaction: Sync {
     ///
     /// `Send + Sync` needed by rayon
     ///
-    /// `ValueBytes` needed by mvmemory snapshot
+    /// `Clone` needed by mvmemory snapshot
     ///
-    type Value: Send + Sync + ValueBytes + Debug;
+    /// `ValueBytes` needed by storage and multi-process execution
+    ///
+    type Value: Clone + Send + Sync + ValueBytes + Debug;
+}
+/// write of transaction to single key
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WriteOp<V> {
+    /// create key which doesn't exist before
+    Create(V),
+    /// modify value of existing key
+    Modify(V),
+    /// delete key
+    Delete,
+}
+impl<V> WriteOp<V> {
+    /// written value,`None` for deletion
+    pub fn into_value(self) -> Option<V> {
+        match self {
+            Self::Create(v) | Self::Modify(v) => Some(v),
+            Self::Delete => None,
+        }
+    }
+    /// borrow written value,keeping kind of write
+    pub fn as_ref(&self) -> WriteOp<&V> {
+        match self {
+            Self::Create(v) => WriteOp::Create(v),
+            Self::Modify(v) => WriteOp::Modify(v),
+            Self::Delete => WriteOp::Delete,
+        }
+    }
+    /// map written value,keeping kind of write
+    pub fn map<U>(self, f: impl FnOnce(V) -> U) -> WriteOp<U> {
+        match self {
+            Self::Create(v) => WriteOp::Create(f(v)),
+            Self::Modify(v) => WriteOp::Modify(f(v)),
+            Self::Delete => WriteOp::Delete,
+        }
+    }
 }
-/// transaction output,which used to get transaction's write set
+/// event emitted by transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// type (or name) of event
+    pub type_tag: String,
+    /// payload of event
+    pub data: Vec<u8>,
+}
+/// transaction output,which used to get transaction's write set,gas used and events
 #[allow(clippy::type_complexity)]
 pub trait TransactionOutput {
     /// transaction type
     type T: Transaction;
-    /// get write set of transaction
+    /// get write set of transaction,deletions are explicit `WriteOp::Delete`
     fn get_write_set(
         &self,
     ) -> Vec<(
         <Self::T as Transaction>::Key,
-        <Self::T as Transaction>::Value,
+        WriteOp<<Self::T as Transaction>::Value>,
     )>;
+    /// get gas used by transaction,0 if vm doesn't charge gas
+    fn get_gas_used(&self) -> u64 {
+        0
+    }
+    /// get events emitted by transaction,in emission order
+    fn get_events(&self) -> Vec<Event> {
+        Vec::new()
+    }
 }
 /// execution engine
 ///
@@ -51,7 +145,10 @@ pub trait VM: Sync {
     /// execution output
     type Output: TransactionOutput<T = Self::T>;
     /// execution error
-    type Error;
+    ///
+    /// `Debug` needed by `ParallelExecutorError::Execution`
+    ///
+    type Error: Debug;
     /// parameter needed by creating new execution engine instance
     type Parameter: Send + Sync + Clone;
     /// create new execution engine instance
@@ -0,0 +1,171 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    core::{ValueBytes, WriteOp},
+    mvmemory::{MVMemory, MVStore, Receipt},
+    types::TxnIndex,
+};
+
+/// committed outcome of a parallel execution recorded in mvmemory,values are serialized to compare them
+pub struct Trace<K> {
+    /// keys read by each transaction,with the transaction they were read from (`None` for storage)
+    read_sets: Vec<Vec<(K, Option<TxnIndex>)>>,
+    write_sets: Vec<Vec<(K, WriteOp<Vec<u8>>)>>,
+    /// gas used and events of each transaction
+    receipts: Vec<Receipt>,
+    snapshot: Vec<(K, Option<Vec<u8>>)>,
+}
+/// first divergence between traces
+pub struct Divergence {
+    /// `read set`,`write set`,`receipt` or `snapshot`
+    pub kind: &'static str,
+    /// `None` for snapshot
+    pub txn_idx: Option<TxnIndex>,
+    /// first diverging key,or diverging part of receipt (`gas used` or `event <index>`)
+    pub key: String,
+}
+impl<K> Trace<K>
+where
+    K: Eq + Hash + Send + Sync + Clone + Debug,
+{
+    pub fn new<V, S>(mvmemory: &MVMemory<K, V, S>, block_size: usize) -> Self
+    where
+        V: Clone + Send + Sync + ValueBytes,
+        S: MVStore<K, V>,
+    {
+        let serialize = |(k, v): (K, Option<V>)| (k, v.map(|v| v.serialize()));
+        Self {
+            read_sets: (0..block_size).map(|i| mvmemory.read_set(i)).collect(),
+            write_sets: (0..block_size)
+                .map(|i| {
+                    mvmemory
+                        .write_set(i)
+                        .into_iter()
+                        .map(|(k, op)| (k, op.map(|v| v.serialize())))
+                        .collect()
+                })
+                .collect(),
+            receipts: (0..block_size).map(|i| mvmemory.receipt(i)).collect(),
+            snapshot: mvmemory
+                .snapshot_cloned()
+                .into_iter()
+                .map(serialize)
+                .collect(),
+        }
+    }
+    /// first divergence of `other` from `self`,transactions are checked in order,
+    /// read set before write set before receipt,snapshot last
+    pub fn diverge(&self, other: &Self) -> Option<Divergence> {
+        for txn_idx in 0..self.read_sets.len().min(other.read_sets.len()) {
+            let divergence = diverging_key(&self.read_sets[txn_idx], &other.read_sets[txn_idx])
+                .map(|key| ("read set", format!("{:?}", key)))
+                .or_else(|| {
+                    diverging_key(&self.write_sets[txn_idx], &other.write_sets[txn_idx])
+                        .map(|key| ("write set", format!("{:?}", key)))
+                })
+                .or_else(|| {
+                    diverging_receipt(&self.receipts[txn_idx], &other.receipts[txn_idx])
+                        .map(|part| ("receipt", part))
+                });
+            if let Some((kind, key)) = divergence {
+                return Some(Divergence {
+                    kind,
+                    txn_idx: Some(txn_idx),
+                    key,
+                });
+            }
+        }
+        diverging_key(&self.snapshot, &other.snapshot).map(|key| Divergence {
+            kind: "snapshot",
+            txn_idx: None,
+            key: format!("{:?}", key),
+        })
+    }
+}
+/// first diverging part of receipts,`gas used` or `event <index>`
+fn diverging_receipt(expected: &Receipt, actual: &Receipt) -> Option<String> {
+    if expected.0 != actual.0 {
+        return Some("gas used".to_string());
+    }
+    (0..expected.1.len().max(actual.1.len()))
+        .find(|i| expected.1.get(*i) != actual.1.get(*i))
+        .map(|i| format!("event {}", i))
+}
+/// first key whose value differs,keys of `expected` are checked in order,then extra keys of `actual`
+fn diverging_key<'a, K, X>(expected: &'a [(K, X)], actual: &'a [(K, X)]) -> Option<&'a K>
+where
+    K: Eq + Hash,
+    X: PartialEq,
+{
+    let actual_map: HashMap<_, _> = actual.iter().map(|(k, x)| (k, x)).collect();
+    let expected_map: HashMap<_, _> = expected.iter().map(|(k, x)| (k, x)).collect();
+    expected
+        .iter()
+        .find(|(k, x)| actual_map.get(k) != Some(&x))
+        .or_else(|| actual.iter().find(|(k, _)| !expected_map.contains_key(k)))
+        .map(|(k, _)| k)
+}
+#[cfg(test)]
+mod tests {
+    use super::Trace;
+    use crate::core::{Event, WriteOp};
+
+    fn trace(read_from: Option<usize>, written: u8, snapshot: &[(usize, u8)]) -> Trace<usize> {
+        let event = Event {
+            type_tag: "written".to_string(),
+            data: vec![written],
+        };
+        Trace {
+            read_sets: vec![vec![], vec![(0, read_from)]],
+            write_sets: vec![
+                vec![(0, WriteOp::Create(vec![1]))],
+                vec![(1, WriteOp::Modify(vec![written]))],
+            ],
+            receipts: vec![(1, vec![]), (1, vec![event])],
+            snapshot: snapshot.iter().map(|(k, v)| (*k, Some(vec![*v]))).collect(),
+        }
+    }
+    #[test]
+    fn diverge() {
+        let expected = trace(Some(0), 2, &[(0, 1), (1, 2)]);
+        assert!(expected
+            .diverge(&trace(Some(0), 2, &[(1, 2), (0, 1)]))
+            .is_none());
+        let divergence = expected
+            .diverge(&trace(None, 3, &[(0, 1), (1, 3)]))
+            .unwrap();
+        assert_eq!(
+            (divergence.kind, divergence.txn_idx, divergence.key.as_str()),
+            ("read set", Some(1), "0")
+        );
+        let divergence = expected
+            .diverge(&trace(Some(0), 3, &[(0, 1), (1, 3)]))
+            .unwrap();
+        assert_eq!(
+            (divergence.kind, divergence.txn_idx, divergence.key.as_str()),
+            ("write set", Some(1), "1")
+        );
+        let mut diverging = trace(Some(0), 2, &[(0, 1), (1, 2)]);
+        diverging.receipts[0].0 = 2;
+        let divergence = expected.diverge(&diverging).unwrap();
+        assert_eq!(
+            (divergence.kind, divergence.txn_idx, divergence.key.as_str()),
+            ("receipt", Some(0), "gas used")
+        );
+        diverging.receipts[0].0 = 1;
+        diverging.receipts[1].1.clear();
+        let divergence = expected.diverge(&diverging).unwrap();
+        assert_eq!(
+            (divergence.kind, divergence.txn_idx, divergence.key.as_str()),
+            ("receipt", Some(1), "event 0")
+        );
+        // extra key of snapshot
+        let divergence = expected
+            .diverge(&trace(Some(0), 2, &[(0, 1), (1, 2), (2, 0)]))
+            .unwrap();
+        assert_eq!(
+            (divergence.kind, divergence.txn_idx, divergence.key.as_str()),
+            ("snapshot", None, "2")
+        );
+    }
+}
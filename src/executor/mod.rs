@@ -1,30 +1,35 @@
 use crate::{
+    adaptive::Throttle,
     core::{Transaction, TransactionOutput, VM},
-    mvmemory::{MVMemory, MVMemoryView},
+    mvmemory::{MVMemory, MVMemoryView, MVStore},
     scheduler::{Scheduler, SchedulerTask, TaskGuard},
     types::Version,
 };
 /// executor
-pub struct Executor<'a, T, V>
+pub struct Executor<'a, T, V, S>
 where
     T: Transaction,
     V: VM<T = T>,
+    S: MVStore<T::Key, T::Value>,
 {
     vm: V,
-    txns: &'a [T],
-    mvmemory: &'a MVMemory<T::Key, T::Value>,
+    txns: &'a [&'a T],
+    mvmemory: &'a MVMemory<T::Key, T::Value, S>,
     scheduler: &'a Scheduler,
+    /// index of worker with throttle of adaptive concurrency
+    throttle: Option<(usize, &'a Throttle)>,
 }
 /// public methods used by parallel executor
-impl<'a, T, V> Executor<'a, T, V>
+impl<'a, T, V, S> Executor<'a, T, V, S>
 where
     T: Transaction,
     V: VM<T = T>,
+    S: MVStore<T::Key, T::Value>,
 {
     pub fn new(
         parameter: V::Parameter,
-        txns: &'a [T],
-        mvmemory: &'a MVMemory<T::Key, T::Value>,
+        txns: &'a [&'a T],
+        mvmemory: &'a MVMemory<T::Key, T::Value, S>,
         scheduler: &'a Scheduler,
     ) -> Self {
         let vm = V::new(parameter);
@@ -33,8 +38,14 @@ where
             txns,
             mvmemory,
             scheduler,
+            throttle: None,
         }
     }
+    /// park between tasks while worker is above the limit of throttle
+    pub fn with_throttle(mut self, worker_idx: usize, throttle: &'a Throttle) -> Self {
+        self.throttle = Some((worker_idx, throttle));
+        self
+    }
     pub fn run(&self) {
         let mut task = SchedulerTask::NoTask;
         loop {
@@ -52,42 +63,85 @@ where
                     SchedulerTask::NoTask
                 }
                 SchedulerTask::Validation(version, guard) => self.try_validate(version, guard),
-                SchedulerTask::NoTask => self.scheduler.next_task(),
+                SchedulerTask::NoTask => match self.throttle {
+                    Some((worker_idx, throttle))
+                        if !throttle.admits(worker_idx, self.scheduler.num_blocked()) =>
+                    {
+                        if self
+                            .scheduler
+                            .park(|blocked| throttle.admits(worker_idx, blocked))
+                        {
+                            SchedulerTask::NoTask
+                        } else {
+                            SchedulerTask::Done
+                        }
+                    }
+                    _ => self.scheduler.next_task(),
+                },
                 SchedulerTask::Done => break,
             }
         }
     }
 }
 /// private methods used by executor itself
-impl<'a, T, V> Executor<'a, T, V>
+impl<'a, T, V, S> Executor<'a, T, V, S>
 where
     T: Transaction,
     V: VM<T = T>,
+    S: MVStore<T::Key, T::Value>,
 {
     fn try_execute<'b>(&self, version: Version, guard: TaskGuard<'b>) -> SchedulerTask<'b> {
         let (txn_idx, incarnation) = version;
-        let txn = &self.txns[txn_idx];
+        let txn = self.txns[txn_idx];
         let mut mvmeory_view = MVMemoryView::new(txn_idx, self.mvmemory, self.scheduler);
-        match self.vm.execute_transaction(txn, &mvmeory_view) {
+        let result = self.vm.execute_transaction(txn, &mvmeory_view);
+        if let Some((_, throttle)) = self.throttle {
+            if throttle.record_execution() {
+                self.scheduler.unpark();
+            }
+        }
+        if self.scheduler.halted() {
+            return SchedulerTask::Done;
+        }
+        match result {
             Ok(output) => {
                 let wrote_new_location = self.mvmemory.record(
                     version,
                     mvmeory_view.take_read_set(),
                     output.get_write_set(),
+                    (output.get_gas_used(), output.get_events()),
                 );
                 self.scheduler
                     .finish_execution(txn_idx, incarnation, wrote_new_location, guard)
             }
-            Err(_e) => {
-                // TODO: how to deal with execute errors?
-                unimplemented!()
+            Err(e) => {
+                // incarnation may have read stale values,so it's validated and re-executed like any output
+                let wrote_new_location = self.mvmemory.record_error(
+                    version,
+                    mvmeory_view.take_read_set(),
+                    format!("{:?}", e),
+                );
+                self.scheduler
+                    .finish_execution(txn_idx, incarnation, wrote_new_location, guard)
             }
         }
     }
     fn try_validate<'b>(&self, version: Version, guard: TaskGuard<'b>) -> SchedulerTask<'b> {
         let (txn_idx, incarnation) = version;
-        let read_set_valid = self.mvmemory.validate_read_set(txn_idx);
-        let aborted = !read_set_valid && self.scheduler.abort(txn_idx, incarnation);
+        let aborted = match self.mvmemory.validate_read_set(txn_idx) {
+            Ok(()) => false,
+            Err((key, writer)) => {
+                let aborted = self.scheduler.abort(txn_idx, incarnation);
+                // only the validation which aborts incarnation is counted
+                if aborted {
+                    self.mvmemory.record_abort(key, writer);
+                    if let Some((_, throttle)) = self.throttle {
+                        throttle.record_abort();
+                    }
+                }
+                aborted
+            }
+        };
         if aborted {
             self.mvmemory.convert_writes_to_estimates(txn_idx);
         }
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::{
+    core::{Event, Transaction, WriteOp, VM},
+    mvmemory::{MVMapError, MVMemory, MVMemoryView, MVStoreConfig},
+    scheduler::Scheduler,
+    types::TxnIndex,
+    ContentionReport, ParallelExecutor,
+};
+
+/// change of key made by transaction,`None` indicates the key doesn't exist (not created yet or deleted)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateChange<K, V> {
+    /// written key
+    pub key: K,
+    /// value seen by transaction
+    pub before: Option<V>,
+    /// write of transaction,`WriteOp::Create` if key didn't exist before
+    pub after: WriteOp<V>,
+}
+/// block executed by `ParallelExecutor::execute`,whose versions are kept until collected.
+///
+/// intra-block state can be queried as seen by any transaction,e.g. for debugging or per-transaction rpc queries,
+/// then `collect` consumes it to get the final snapshot.
+pub struct ExecutedBlock<'a, T, V, C>
+where
+    T: Transaction,
+    V: VM<T = T>,
+    C: MVStoreConfig<T::Key, T::Value>,
+{
+    executor: &'a ParallelExecutor<T, V, C>,
+    mvmemory: MVMemory<T::Key, T::Value, C::Store>,
+    /// scheduler of finished execution,used by views of simulated transactions
+    scheduler: Scheduler,
+    parameter: V::Parameter,
+    block_size: usize,
+}
+impl<'a, T, V, C> ExecutedBlock<'a, T, V, C>
+where
+    T: Transaction,
+    V: VM<T = T>,
+    C: MVStoreConfig<T::Key, T::Value>,
+{
+    pub(crate) fn new(
+        executor: &'a ParallelExecutor<T, V, C>,
+        mvmemory: MVMemory<T::Key, T::Value, C::Store>,
+        scheduler: Scheduler,
+        parameter: V::Parameter,
+        block_size: usize,
+    ) -> Self {
+        Self {
+            executor,
+            mvmemory,
+            scheduler,
+            parameter,
+            block_size,
+        }
+    }
+    /// number of transactions of block
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+    /// value of key seen by transaction `txn_idx` (`0 <= txn_idx <= block_size`,`block_size` sees the whole block),
+    /// which is written by the highest transaction below it,with the index of that transaction.
+    ///
+    /// `None` if no transaction below `txn_idx` writes key,i.e. transaction reads it from base state,
+    /// value is `None` if that transaction deletes key.
+    #[allow(clippy::type_complexity)]
+    pub fn value_at(
+        &self,
+        key: &T::Key,
+        txn_idx: TxnIndex,
+    ) -> Option<(TxnIndex, Option<Arc<T::Value>>)> {
+        self.check_position(txn_idx);
+        match self.mvmemory.read(key, txn_idx) {
+            Ok(output) => Some((output.version().0, output.value())),
+            Err(MVMapError::NotFound) => None,
+            Err(MVMapError::ReadError(_)) => {
+                unreachable!("executed block should not contain estimates")
+            }
+        }
+    }
+    /// execute an extra transaction at position `txn_idx` (`0 <= txn_idx <= block_size`) against executed block,
+    /// i.e. it sees writes of transactions below `txn_idx`.
+    ///
+    /// the block is not changed,output of simulated transaction is discarded unless used by caller.
+    pub fn simulate(&self, txn: &T, txn_idx: TxnIndex) -> Result<V::Output, V::Error> {
+        self.check_position(txn_idx);
+        let vm = V::new(self.parameter.clone());
+        let view = MVMemoryView::new(txn_idx, &self.mvmemory, &self.scheduler);
+        vm.execute_transaction(txn, &view)
+    }
+    /// total gas used by committed transactions
+    pub fn gas_used(&self) -> u64 {
+        self.mvmemory.gas_used()
+    }
+    /// event stream of block,events of committed transactions with their indices,in block order then emission order
+    pub fn events(&self) -> Vec<(TxnIndex, Event)> {
+        self.mvmemory.events()
+    }
+    /// number of aborted incarnations during execution
+    pub fn num_aborts(&self) -> usize {
+        self.scheduler.num_aborts()
+    }
+    /// keys and writer transactions ranked by aborts they caused during execution
+    pub fn contention(&self) -> ContentionReport<T::Key> {
+        self.mvmemory.contention()
+    }
+    /// own write set of each committed transaction in block order,keys in write order of transaction,
+    /// with value before and after each write,e.g. for receipts and state-sync.
+    ///
+    /// `base` reads base state of block,used for keys not written by lower transactions.
+    pub fn changes<B>(&self, base: B) -> Vec<Vec<StateChange<T::Key, T::Value>>>
+    where
+        B: Fn(&T::Key) -> Option<T::Value> + Sync,
+    {
+        (0..self.block_size)
+            .into_par_iter()
+            .map(|txn_idx| {
+                self.mvmemory
+                    .changes(txn_idx)
+                    .into_iter()
+                    .map(|(key, before, after)| StateChange {
+                        before: before.unwrap_or_else(|| base(&key)),
+                        key,
+                        after,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// snapshot of block in configured order of executor,same as `ParallelExecutor::execute_transactions`
+    #[allow(clippy::type_complexity)]
+    pub fn collect(self) -> Vec<(T::Key, Option<T::Value>)> {
+        self.executor.collect(self.mvmemory)
+    }
+    fn check_position(&self, txn_idx: TxnIndex) {
+        assert!(
+            txn_idx <= self.block_size,
+            "position {} is out of block of {} transactions",
+            txn_idx,
+            self.block_size
+        );
+    }
+}
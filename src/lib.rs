@@ -1,20 +1,56 @@
 #![deny(missing_docs)]
 //! block_stm implementation
+/// adaptive concurrency of parallel execution
+mod adaptive;
+/// state commitment over output of block
+#[cfg(feature = "commitment")]
+pub mod commitment;
+/// hot-key contention report of parallel execution
+mod contention;
 /// abstract traits,used to implement user own execution engine
 pub mod core;
+/// determinism checking across repeated parallel executions
+mod determinism;
 mod executor;
+/// intra-block state queries of executed block
+mod history;
+/// multi-process execution,a coordinator serving worker processes which host the `VM`
+pub mod multiprocess;
 mod mvmemory;
+/// conflict-free partitioning of transactions,used by partitioned parallel execution
+pub mod partition;
+/// conflict-aware transaction reordering,used before parallel execution
+pub mod reorder;
 mod scheduler;
+/// persistent storage,used as base state of blocks
+pub mod storage;
 /// test utils used by benches and tests
 pub mod test_utils;
 mod types;
 
 use crate::core::{Transaction, VM};
+pub use adaptive::AdaptiveConcurrency;
+use adaptive::Throttle;
+#[cfg(feature = "commitment")]
+use commitment::{Commitment, CommitmentHasher};
+pub use contention::ContentionReport;
+use determinism::Trace;
 use executor::Executor;
+pub use history::{ExecutedBlock, StateChange};
 use mvmemory::MVMemory;
+pub use mvmemory::{
+    DenseKey, DenseMVMap, DenseMVMapConfig, MVMap, MVMapConfig, MVMapError, MVMapOutput, MVStore,
+    MVStoreConfig,
+};
 use once_cell::sync::Lazy;
-use scheduler::Scheduler;
-use std::marker::PhantomData;
+use reorder::KeySet;
+use scheduler::{Scheduler, Watchdog};
+use std::{
+    cmp::Ordering, collections::HashMap, fmt::Debug, hash::Hash, marker::PhantomData,
+    time::Duration,
+};
+use thiserror::Error;
+use types::TxnIndex;
 
 static RAYON_EXEC_POOL: Lazy<rayon::ThreadPool> = Lazy::new(|| {
     rayon::ThreadPoolBuilder::new()
@@ -23,13 +59,159 @@ static RAYON_EXEC_POOL: Lazy<rayon::ThreadPool> = Lazy::new(|| {
         .build()
         .unwrap()
 });
-/// parallel executor
-pub struct ParallelExecutor<T, V>
+/// error from parallel executor
+#[derive(Debug, Error)]
+pub enum ParallelExecutorError {
+    /// no transaction changed state within watchdog interval,carry the dumped scheduler state
+    #[error("no progress within {0:?},scheduler state:\n{1}")]
+    NoProgress(Duration, String),
+    /// vm failed to execute committed transaction,which fails block
+    #[error("transaction {txn_idx} failed to execute: {error}")]
+    Execution {
+        /// index of failed transaction in block
+        txn_idx: TxnIndex,
+        /// error of vm,formatted by `Debug`
+        error: String,
+    },
+    /// repeated executions of the same block diverge,see `ParallelExecutor::verify_determinism`
+    #[error("run {run} (concurrency level {concurrency_level}) diverges from the first run in {kind} of transaction {txn_idx:?} at key {key}")]
+    NonDeterministic {
+        /// index of diverging run
+        run: usize,
+        /// concurrency level of diverging run
+        concurrency_level: usize,
+        /// `read set`,`write set`,`receipt` or `snapshot`
+        kind: &'static str,
+        /// first diverging transaction,`None` for snapshot
+        txn_idx: Option<TxnIndex>,
+        /// first diverging key,or diverging part of receipt (`gas used` or `event <index>`)
+        key: String,
+    },
+}
+/// report of single parallel execution,`K` is the key type of transactions
+#[derive(Debug, Clone)]
+pub struct ExecutionReport<K> {
+    /// execute walltime
+    pub execute_time: Duration,
+    /// collect walltime
+    pub collect_time: Duration,
+    /// number of aborted incarnations
+    pub num_aborts: usize,
+    /// total gas used by committed transactions
+    pub gas_used: u64,
+    /// keys and writer transactions ranked by aborts they caused
+    pub contention: ContentionReport<K>,
+}
+impl<K> Default for ExecutionReport<K> {
+    fn default() -> Self {
+        Self {
+            execute_time: Duration::default(),
+            collect_time: Duration::default(),
+            num_aborts: 0,
+            gas_used: 0,
+            contention: ContentionReport::default(),
+        }
+    }
+}
+/// order of keys in execution output
+pub enum SnapshotOrder<K> {
+    /// iteration order of mvstore,which changes from run to run
+    Unordered,
+    /// ordered by the first transaction writing the key,then by position of key in its write set
+    FirstWriter,
+    /// sorted by given comparator,see `SnapshotOrder::sorted`
+    Sorted(fn(&K, &K) -> Ordering),
+}
+impl<K: Ord> SnapshotOrder<K> {
+    /// sorted by key
+    pub fn sorted() -> Self {
+        Self::Sorted(K::cmp)
+    }
+}
+impl<K> Clone for SnapshotOrder<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<K> Copy for SnapshotOrder<K> {}
+impl<K> SnapshotOrder<K>
+where
+    K: Eq + Hash,
+{
+    fn by_first_writer(&self) -> bool {
+        matches!(self, Self::FirstWriter)
+    }
+    /// sort snapshot in place,`first_writes` must be present if ordered by first writer
+    fn sort<V>(
+        &self,
+        snapshot: &mut [(K, Option<V>)],
+        first_writes: Option<HashMap<K, (TxnIndex, usize)>>,
+    ) {
+        match self {
+            Self::Unordered => {}
+            Self::FirstWriter => {
+                let first_writes = first_writes.expect("first writes needed by snapshot order");
+                snapshot.sort_unstable_by_key(|(key, _)| first_writes[key]);
+            }
+            Self::Sorted(cmp) => snapshot.sort_unstable_by(|(a, _), (b, _)| cmp(a, b)),
+        }
+    }
+}
+/// long-lived execution context,which reuses mvmemory and scheduler allocations across blocks,
+/// i.e. per-transaction slots and the key table of mvstore,versions of keys are still allocated per block.
+pub struct ExecutorContext<K, V, S = MVMap<K, V>> {
+    mvmemory: MVMemory<K, V, S>,
+    scheduler: Scheduler,
+}
+impl<K, V> ExecutorContext<K, V>
+where
+    K: Eq + Hash + Send + Sync + Clone + Debug,
+    V: Clone + Send + Sync,
+{
+    /// create an empty execution context with default `MVMap`
+    pub fn new() -> Self {
+        Self::with_store(MVMapConfig::default().build())
+    }
+}
+impl<K, V, S> ExecutorContext<K, V, S>
+where
+    K: Eq + Hash + Send + Sync + Clone + Debug,
+    V: Clone + Send + Sync,
+    S: MVStore<K, V>,
+{
+    /// create an empty execution context with given mvstore
+    pub fn with_store(store: S) -> Self {
+        Self {
+            mvmemory: MVMemory::new(0, store),
+            scheduler: Scheduler::new(0),
+        }
+    }
+    /// clear state of last block (even if it failed or panicked) and resize for next block
+    fn prepare(&mut self, block_size: usize) {
+        self.mvmemory.clear();
+        self.mvmemory.resize(block_size);
+        self.scheduler.reset(block_size);
+    }
+}
+impl<K, V> Default for ExecutorContext<K, V>
+where
+    K: Eq + Hash + Send + Sync + Clone + Debug,
+    V: Clone + Send + Sync,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+/// parallel executor,`C` is the config of mvstore used by each block
+pub struct ParallelExecutor<T, V, C = MVMapConfig>
 where
     T: Transaction,
     V: VM<T = T>,
 {
     concurrency_level: usize,
+    watchdog: Option<Duration>,
+    store_config: C,
+    snapshot_order: SnapshotOrder<T::Key>,
     phantom: PhantomData<(T, V)>,
 }
 impl<T, V> ParallelExecutor<T, V>
@@ -46,63 +228,483 @@ where
         );
         Self {
             concurrency_level,
+            watchdog: None,
+            store_config: MVMapConfig::default(),
+            snapshot_order: SnapshotOrder::Unordered,
             phantom: PhantomData,
         }
     }
+}
+impl<T, V, C> ParallelExecutor<T, V, C>
+where
+    T: Transaction,
+    V: VM<T = T>,
+    C: MVStoreConfig<T::Key, T::Value>,
+{
+    /// use mvstore created by given config (e.g. `DenseMVMapConfig`) instead of default `MVMap`
+    pub fn with_store<C2>(self, store_config: C2) -> ParallelExecutor<T, V, C2>
+    where
+        C2: MVStoreConfig<T::Key, T::Value>,
+    {
+        ParallelExecutor {
+            concurrency_level: self.concurrency_level,
+            watchdog: self.watchdog,
+            store_config,
+            snapshot_order: self.snapshot_order,
+            phantom: PhantomData,
+        }
+    }
+    /// enable watchdog,which aborts execution if no transaction changed state within `interval`
+    pub fn with_watchdog(mut self, interval: Duration) -> Self {
+        self.watchdog = Some(interval);
+        self
+    }
+    /// order keys of execution output,default is `SnapshotOrder::Unordered`
+    pub fn with_snapshot_order(mut self, snapshot_order: SnapshotOrder<T::Key>) -> Self {
+        self.snapshot_order = snapshot_order;
+        self
+    }
     /// parallel execute txns with given view
+    #[allow(clippy::type_complexity)]
     pub fn execute_transactions(
         &self,
-        txns: &Vec<T>,
+        txns: &[T],
         parameter: V::Parameter,
-    ) -> Vec<(T::Key, Option<T::Value>)> {
-        let txns_num = txns.len();
-        let mvmemory = MVMemory::new(txns_num);
-        let scheduler = Scheduler::new(txns_num);
-        RAYON_EXEC_POOL.scope(|s| {
-            for _ in 0..self.concurrency_level {
-                s.spawn(|_| {
-                    let executor =
-                        Executor::<T, V>::new(parameter.clone(), txns, &mvmemory, &scheduler);
-                    executor.run();
-                });
-            }
+    ) -> Result<Vec<(T::Key, Option<T::Value>)>, ParallelExecutorError> {
+        let txns: Vec<&T> = txns.iter().collect();
+        let (mvmemory, _) = self.run_block(&txns, self.concurrency_level, parameter)?;
+        Ok(self.collect(mvmemory))
+    }
+    /// parallel execute txns,but keep versions of block instead of collecting them,
+    /// so that state seen by each transaction can be queried (see `ExecutedBlock`) before collecting snapshot.
+    pub fn execute(
+        &self,
+        txns: &[T],
+        parameter: V::Parameter,
+    ) -> Result<ExecutedBlock<'_, T, V, C>, ParallelExecutorError> {
+        let txns: Vec<&T> = txns.iter().collect();
+        let (mvmemory, scheduler) =
+            self.run_block(&txns, self.concurrency_level, parameter.clone())?;
+        Ok(ExecutedBlock::new(
+            self,
+            mvmemory,
+            scheduler,
+            parameter,
+            txns.len(),
+        ))
+    }
+    /// parallel execute txns with given context,context is cleared before execution,so it can be reused by next block
+    #[allow(clippy::type_complexity)]
+    pub fn execute_transactions_with_context<S: MVStore<T::Key, T::Value>>(
+        &self,
+        context: &mut ExecutorContext<T::Key, T::Value, S>,
+        txns: &[T],
+        parameter: V::Parameter,
+    ) -> Result<Vec<(T::Key, Option<T::Value>)>, ParallelExecutorError> {
+        let txns: Vec<&T> = txns.iter().collect();
+        context.prepare(txns.len());
+        self.run(
+            &[Shard::new(
+                &txns,
+                &context.mvmemory,
+                &context.scheduler,
+                self.concurrency_level,
+            )],
+            parameter,
+        )?;
+        Ok(self.collect_cloned(&context.mvmemory))
+    }
+    /// parallel execute txns with adaptive concurrency,which is shared by consecutive blocks.
+    ///
+    /// block starts with the concurrency level learned from recent blocks (at most `concurrency_level` of executor),
+    /// then active workers shrink while abort rate is high (down to a single worker,i.e. effectively sequential execution)
+    /// and grow back while it's low.
+    #[allow(clippy::type_complexity)]
+    pub fn execute_transactions_adaptive(
+        &self,
+        adaptive: &mut AdaptiveConcurrency,
+        txns: &[T],
+        parameter: V::Parameter,
+    ) -> Result<Vec<(T::Key, Option<T::Value>)>, ParallelExecutorError> {
+        let start = std::time::Instant::now();
+        let txns: Vec<&T> = txns.iter().collect();
+        let mvmemory = MVMemory::new(txns.len(), self.store_config.build());
+        let scheduler = Scheduler::new(txns.len());
+        let concurrency_level = adaptive.concurrency_level().min(self.concurrency_level);
+        let throttle = Throttle::new(concurrency_level, self.concurrency_level);
+        self.run(
+            &[
+                Shard::new(&txns, &mvmemory, &scheduler, self.concurrency_level)
+                    .with_throttle(&throttle),
+            ],
+            parameter,
+        )?;
+        let snapshot = self.collect(mvmemory);
+        adaptive.observe(
+            concurrency_level,
+            throttle.limit(),
+            txns.len(),
+            start.elapsed(),
+        );
+        Ok(snapshot)
+    }
+    /// parallel execute txns partitioned by their declared (or predicted) key sets,
+    /// each conflict-free partition runs on its own mvmemory and scheduler,then snapshots are merged.
+    ///
+    /// fall back to unified execution if partitions conflict at runtime (e.g. mispredicted key sets).
+    #[allow(clippy::type_complexity)]
+    pub fn execute_transactions_partitioned<F>(
+        &self,
+        txns: &[T],
+        key_set: F,
+        parameter: V::Parameter,
+    ) -> Result<Vec<(T::Key, Option<T::Value>)>, ParallelExecutorError>
+    where
+        F: Fn(&T) -> KeySet<T::Key>,
+    {
+        let shards = partition::shard(partition::partition(txns, key_set), self.concurrency_level);
+        if shards.len() <= 1 {
+            return self.execute_transactions(txns, parameter);
+        }
+        let concurrency_levels = partition::split_concurrency(&shards, self.concurrency_level);
+        let shard_txns: Vec<Vec<&T>> = shards
+            .iter()
+            .map(|shard| shard.iter().map(|&txn_idx| &txns[txn_idx]).collect())
+            .collect();
+        let mvmemories: Vec<_> = shard_txns
+            .iter()
+            .map(|txns| MVMemory::new(txns.len(), self.store_config.build()))
+            .collect();
+        let schedulers: Vec<_> = shard_txns
+            .iter()
+            .map(|txns| Scheduler::new(txns.len()))
+            .collect();
+        let run_shards: Vec<_> = shard_txns
+            .iter()
+            .zip(&mvmemories)
+            .zip(&schedulers)
+            .zip(concurrency_levels)
+            .zip(&shards)
+            .map(
+                |((((txns, mvmemory), scheduler), concurrency_level), shard)| {
+                    Shard::new(txns, mvmemory, scheduler, concurrency_level).with_indices(shard)
+                },
+            )
+            .collect();
+        self.run(&run_shards, parameter.clone())?;
+        drop(run_shards);
+        if Self::cross_shard_conflict(&mvmemories) {
+            #[cfg(feature = "tracing")]
+            crate::rayon_debug!("partitions conflict at runtime,fall back to unified execution");
+            return self.execute_transactions(txns, parameter);
+        }
+        // first writes of shards,with transaction indices mapped back to block
+        let first_writes = self.snapshot_order.by_first_writer().then(|| {
+            mvmemories
+                .iter()
+                .zip(&shards)
+                .flat_map(|(mvmemory, shard)| {
+                    mvmemory
+                        .first_writes()
+                        .into_iter()
+                        .map(|(key, (txn_idx, position))| (key, (shard[txn_idx], position)))
+                })
+                .collect()
         });
-        mvmemory.snapshot()
+        let mut snapshot: Vec<_> = mvmemories
+            .into_iter()
+            .flat_map(|mvmemory| mvmemory.snapshot())
+            .collect();
+        self.snapshot_order.sort(&mut snapshot, first_writes);
+        Ok(snapshot)
+    }
+    /// parallel execute txns,then commit to output with given hasher,
+    /// which computes merkle root over snapshot and accumulator of write sets (see `commitment`).
+    #[cfg(feature = "commitment")]
+    #[allow(clippy::type_complexity)]
+    pub fn execute_transactions_with_commitment<H>(
+        &self,
+        txns: &[T],
+        parameter: V::Parameter,
+        hasher: &H,
+    ) -> Result<(Vec<(T::Key, Option<T::Value>)>, Commitment<H::Digest>), ParallelExecutorError>
+    where
+        T::Key: core::WireBytes,
+        H: CommitmentHasher<T::Key>,
+    {
+        use rayon::prelude::*;
+        let txns: Vec<&T> = txns.iter().collect();
+        let (mvmemory, _) = self.run_block(&txns, self.concurrency_level, parameter)?;
+        let write_sets: Vec<_> = (0..txns.len())
+            .into_par_iter()
+            .map(|txn_idx| {
+                mvmemory
+                    .write_set(txn_idx)
+                    .into_iter()
+                    .map(|(k, op)| (k, op.into_value()))
+                    .collect()
+            })
+            .collect();
+        let accumulator = commitment::accumulate(hasher, &write_sets);
+        let snapshot = self.collect(mvmemory);
+        let state_root = commitment::merkle_root(hasher, &snapshot);
+        Ok((
+            snapshot,
+            Commitment {
+                state_root,
+                accumulator,
+            },
+        ))
+    }
+    /// parallel execute the same block `runs` times,with concurrency level cycling from `concurrency_level` down to 1,
+    /// each run should commit the same read sets (keys and transactions read from),write sets,
+    /// receipts (gas used and events) and snapshot as the first one.
+    ///
+    /// return snapshot of the first run,or `ParallelExecutorError::NonDeterministic` with the first diverging transaction and key.
+    #[allow(clippy::type_complexity)]
+    pub fn verify_determinism(
+        &self,
+        txns: &[T],
+        parameter: V::Parameter,
+        runs: usize,
+    ) -> Result<Vec<(T::Key, Option<T::Value>)>, ParallelExecutorError> {
+        assert!(runs > 0, "at least one run");
+        let txns: Vec<&T> = txns.iter().collect();
+        let mut first = None;
+        for run in 0..runs {
+            let concurrency_level = self.concurrency_level - run % self.concurrency_level;
+            let (mvmemory, _) = self.run_block(&txns, concurrency_level, parameter.clone())?;
+            let trace = Trace::new(&mvmemory, txns.len());
+            match &first {
+                None => first = Some((mvmemory, trace)),
+                Some((_, expected)) => {
+                    if let Some(divergence) = expected.diverge(&trace) {
+                        return Err(ParallelExecutorError::NonDeterministic {
+                            run,
+                            concurrency_level,
+                            kind: divergence.kind,
+                            txn_idx: divergence.txn_idx,
+                            key: divergence.key,
+                        });
+                    }
+                }
+            }
+        }
+        let (mvmemory, _) = first.expect("at least one run");
+        Ok(self.collect(mvmemory))
     }
     /// execute transactions for benchmark
+    #[allow(clippy::type_complexity)]
     pub fn execute_transactions_benchmark(
         &self,
-        txns: &Vec<T>,
+        txns: &[T],
         parameter: V::Parameter,
-    ) -> (
-        Vec<(T::Key, Option<T::Value>)>,
-        std::time::Duration,
-        std::time::Duration,
-    ) {
+    ) -> Result<(Vec<(T::Key, Option<T::Value>)>, ExecutionReport<T::Key>), ParallelExecutorError>
+    {
         use std::time::Instant;
-        let txns_num = txns.len();
-        let mvmemory = MVMemory::new(txns_num);
-        let scheduler = Scheduler::new(txns_num);
+        let txns: Vec<&T> = txns.iter().collect();
 
         let execute_start = Instant::now();
 
-        RAYON_EXEC_POOL.scope(|s| {
-            for _ in 0..self.concurrency_level {
-                s.spawn(|_| {
-                    let executor =
-                        Executor::<T, V>::new(parameter.clone(), txns, &mvmemory, &scheduler);
-                    executor.run();
-                });
-            }
-        });
+        let (mvmemory, scheduler) = self.run_block(&txns, self.concurrency_level, parameter)?;
 
-        let execute_end = execute_start.elapsed();
+        let execute_time = execute_start.elapsed();
+        let gas_used = mvmemory.gas_used();
+        let contention = mvmemory.contention();
 
         let collect_start = Instant::now();
 
-        let result = mvmemory.snapshot();
+        let result = self.collect(mvmemory);
 
-        let collect_end = collect_start.elapsed();
-        (result, execute_end, collect_end)
+        let collect_time = collect_start.elapsed();
+        Ok((
+            result,
+            ExecutionReport {
+                execute_time,
+                collect_time,
+                num_aborts: scheduler.num_aborts(),
+                gas_used,
+                contention,
+            },
+        ))
+    }
+}
+/// transactions executed by their own mvmemory and scheduler
+struct Shard<'a, T: Transaction, S> {
+    txns: &'a [&'a T],
+    mvmemory: &'a MVMemory<T::Key, T::Value, S>,
+    scheduler: &'a Scheduler,
+    concurrency_level: usize,
+    throttle: Option<&'a Throttle>,
+    /// indices of txns in block,`None` if shard is the whole block
+    indices: Option<&'a [TxnIndex]>,
+}
+impl<'a, T: Transaction, S> Shard<'a, T, S> {
+    fn new(
+        txns: &'a [&'a T],
+        mvmemory: &'a MVMemory<T::Key, T::Value, S>,
+        scheduler: &'a Scheduler,
+        concurrency_level: usize,
+    ) -> Self {
+        Self {
+            txns,
+            mvmemory,
+            scheduler,
+            concurrency_level,
+            throttle: None,
+            indices: None,
+        }
+    }
+    /// limit active workers by throttle of adaptive concurrency
+    fn with_throttle(mut self, throttle: &'a Throttle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+    /// shard of partitioned block,whose txns are at given indices of block
+    fn with_indices(mut self, indices: &'a [TxnIndex]) -> Self {
+        self.indices = Some(indices);
+        self
+    }
+    /// committed transaction of shard which failed to execute,with transaction index mapped back to block
+    fn failure(&self) -> Option<ParallelExecutorError>
+    where
+        S: MVStore<T::Key, T::Value>,
+    {
+        self.mvmemory
+            .first_error()
+            .map(|(txn_idx, error)| ParallelExecutorError::Execution {
+                txn_idx: self.indices.map_or(txn_idx, |indices| indices[txn_idx]),
+                error,
+            })
+    }
+}
+/// private methods used by parallel executor itself
+impl<T, V, C> ParallelExecutor<T, V, C>
+where
+    T: Transaction,
+    V: VM<T = T>,
+    C: MVStoreConfig<T::Key, T::Value>,
+{
+    /// run whole block on new mvmemory and scheduler with given concurrency level
+    #[allow(clippy::type_complexity)]
+    fn run_block(
+        &self,
+        txns: &[&T],
+        concurrency_level: usize,
+        parameter: V::Parameter,
+    ) -> Result<(MVMemory<T::Key, T::Value, C::Store>, Scheduler), ParallelExecutorError> {
+        let mvmemory = MVMemory::new(txns.len(), self.store_config.build());
+        let scheduler = Scheduler::new(txns.len());
+        self.run(
+            &[Shard::new(txns, &mvmemory, &scheduler, concurrency_level)],
+            parameter,
+        )?;
+        Ok((mvmemory, scheduler))
+    }
+    fn run<S: MVStore<T::Key, T::Value>>(
+        &self,
+        shards: &[Shard<T, S>],
+        parameter: V::Parameter,
+    ) -> Result<(), ParallelExecutorError> {
+        let parameter = &parameter;
+        let spawn_executors = || {
+            RAYON_EXEC_POOL.scope(|s| {
+                for shard in shards {
+                    for worker_idx in 0..shard.concurrency_level {
+                        s.spawn(move |_| {
+                            let mut executor = Executor::<T, V, S>::new(
+                                parameter.clone(),
+                                shard.txns,
+                                shard.mvmemory,
+                                shard.scheduler,
+                            );
+                            if let Some(throttle) = shard.throttle {
+                                executor = executor.with_throttle(worker_idx, throttle);
+                            }
+                            executor.run();
+                        });
+                    }
+                }
+            })
+        };
+        let result = match self.watchdog {
+            None => {
+                spawn_executors();
+                Ok(())
+            }
+            // watchdog runs outside of rayon pool,which may be fully occupied by executors
+            Some(interval) => crossbeam::scope(|s| {
+                let (stop_tx, stop_rx) = crossbeam::channel::bounded::<()>(1);
+                let watchdogs: Vec<_> = shards
+                    .iter()
+                    .map(|shard| {
+                        let (scheduler, stop_rx) = (shard.scheduler, stop_rx.clone());
+                        s.spawn(move |_| Watchdog::new(scheduler, interval).watch(stop_rx))
+                    })
+                    .collect();
+                spawn_executors();
+                drop(stop_tx);
+                watchdogs
+                    .into_iter()
+                    .map(|watchdog| watchdog.join().expect("watchdog panicked"))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .expect("watchdog scope error")
+            .map(|_| ())
+            .map_err(|state| ParallelExecutorError::NoProgress(interval, state.to_string())),
+        };
+        result?;
+        // errors of speculative incarnations are gone once their transactions are re-executed
+        match shards.iter().find_map(|shard| shard.failure()) {
+            Some(failure) => Err(failure),
+            None => Ok(()),
+        }
+    }
+    /// snapshot of mvmemory in configured order
+    #[allow(clippy::type_complexity)]
+    fn collect<S: MVStore<T::Key, T::Value>>(
+        &self,
+        mvmemory: MVMemory<T::Key, T::Value, S>,
+    ) -> Vec<(T::Key, Option<T::Value>)> {
+        let first_writes = self
+            .snapshot_order
+            .by_first_writer()
+            .then(|| mvmemory.first_writes());
+        let mut snapshot = mvmemory.snapshot();
+        self.snapshot_order.sort(&mut snapshot, first_writes);
+        snapshot
+    }
+    /// same as `collect`,but keep mvmemory for reuse
+    #[allow(clippy::type_complexity)]
+    fn collect_cloned<S: MVStore<T::Key, T::Value>>(
+        &self,
+        mvmemory: &MVMemory<T::Key, T::Value, S>,
+    ) -> Vec<(T::Key, Option<T::Value>)> {
+        let first_writes = self
+            .snapshot_order
+            .by_first_writer()
+            .then(|| mvmemory.first_writes());
+        let mut snapshot = mvmemory.snapshot_cloned();
+        self.snapshot_order.sort(&mut snapshot, first_writes);
+        snapshot
+    }
+    /// whether a key written by one shard is accessed by another shard
+    fn cross_shard_conflict<S: MVStore<T::Key, T::Value>>(
+        mvmemories: &[MVMemory<T::Key, T::Value, S>],
+    ) -> bool {
+        let mut writers = HashMap::new();
+        for (shard_idx, mvmemory) in mvmemories.iter().enumerate() {
+            for key in mvmemory.written_keys() {
+                if *writers.entry(key).or_insert(shard_idx) != shard_idx {
+                    return true;
+                }
+            }
+        }
+        mvmemories.iter().enumerate().any(|(shard_idx, mvmemory)| {
+            mvmemory
+                .read_keys()
+                .iter()
+                .any(|key| matches!(writers.get(key), Some(writer) if *writer != shard_idx))
+        })
     }
 }
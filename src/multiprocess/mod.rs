@@ -0,0 +1,353 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io::{self, BufReader, BufWriter, Read, Write},
+    marker::PhantomData,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    core::{Event, Transaction, TransactionOutput, ValueBytes, WriteOp, VM},
+    mvmemory::{MVMemoryView, ReadResult, RemoteRead},
+    types::Mutex,
+    ParallelExecutor, ParallelExecutorError, SnapshotOrder,
+};
+
+use self::protocol::{CoordinatorMessage, Output, ReadReply, WorkerMessage};
+/// wire encoding of keys and transactions,moved to `core` since storage also persists them
+pub use crate::core::WireBytes;
+
+/// messages exchanged between coordinator and workers
+mod protocol;
+
+/// time given to worker to exit after shutdown,before it's killed
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+/// respawns of worker to retry a transaction after its connection is broken
+const RESPAWN_RETRIES: usize = 1;
+
+/// coordinator of multi-process execution,which owns scheduler and mvmemory of block,
+/// and serves reads,writes and tasks to worker processes hosting the real `VM`.
+///
+/// each worker is driven by one executor of coordinator,through its stdin/stdout.
+pub struct Coordinator<T>
+where
+    T: Transaction + WireBytes,
+    T::Key: WireBytes,
+{
+    executor: ParallelExecutor<T, RemoteVM<T>>,
+    workers: Arc<WorkerPool>,
+    /// blocks are executed one at a time,since each executor of block holds one worker
+    running: Mutex<()>,
+}
+impl<T> Coordinator<T>
+where
+    T: Transaction + WireBytes,
+    T::Key: WireBytes,
+{
+    /// spawn `num_workers` (0 < `num_workers` <= `num_cpus::get()`) worker processes running `program` with `args`,
+    /// see `serve` for the worker side.
+    pub fn spawn<P, A, S>(program: P, args: A, num_workers: usize) -> io::Result<Self>
+    where
+        P: AsRef<OsStr>,
+        A: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let executor = ParallelExecutor::new(num_workers);
+        let pool = WorkerPool {
+            program: program.as_ref().to_os_string(),
+            args: args
+                .into_iter()
+                .map(|arg| arg.as_ref().to_os_string())
+                .collect(),
+            idle: Mutex::new(Vec::new()),
+        };
+        let workers = (0..num_workers)
+            .map(|_| pool.spawn())
+            .collect::<io::Result<_>>()?;
+        *pool.idle.lock() = workers;
+        Ok(Self {
+            executor,
+            workers: Arc::new(pool),
+            running: Mutex::new(()),
+        })
+    }
+    /// enable watchdog of coordinator,see `ParallelExecutor::with_watchdog`
+    pub fn with_watchdog(mut self, interval: Duration) -> Self {
+        self.executor = self.executor.with_watchdog(interval);
+        self
+    }
+    /// order keys of execution output,see `ParallelExecutor::with_snapshot_order`
+    pub fn with_snapshot_order(mut self, snapshot_order: SnapshotOrder<T::Key>) -> Self {
+        self.executor = self.executor.with_snapshot_order(snapshot_order);
+        self
+    }
+    /// parallel execute txns on workers,workers are kept for next block,overlapping calls wait for each other.
+    ///
+    /// vm error of worker is the output of its incarnation,like in-process execution,
+    /// block fails with `ParallelExecutorError::Execution` only if a committed transaction fails.
+    /// a worker whose connection is broken is respawned and the transaction is retried,
+    /// so is a worker lost by a previous block once it's needed.
+    #[allow(clippy::type_complexity)]
+    pub fn execute_transactions(
+        &self,
+        txns: &[T],
+    ) -> Result<Vec<(T::Key, Option<T::Value>)>, ParallelExecutorError> {
+        let _running = self.running.lock();
+        self.executor
+            .execute_transactions(txns, self.workers.clone())
+    }
+}
+/// serve coordinator on `input`/`output` (usually stdin/stdout of worker process) with vm created by `parameter`,
+/// return when coordinator shuts down or disconnects.
+pub fn serve<V, R, W>(parameter: V::Parameter, input: R, output: W) -> io::Result<()>
+where
+    V: VM,
+    V::T: WireBytes,
+    <V::T as Transaction>::Key: WireBytes,
+    R: Read + Send,
+    W: Write + Send,
+{
+    let vm = V::new(parameter);
+    let channel = Channel(Mutex::new((BufReader::new(input), BufWriter::new(output))));
+    loop {
+        let message = match CoordinatorMessage::read_from(&mut channel.0.lock().0) {
+            Ok(message) => message,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        match message {
+            CoordinatorMessage::Execute(txn_idx, txn) => {
+                let txn = V::T::from_bytes(&txn);
+                let view = MVMemoryView::remote(txn_idx, &channel);
+                let reply = match vm.execute_transaction(&txn, &view) {
+                    Ok(output) => WorkerMessage::Output(Output {
+                        write_set: output
+                            .get_write_set()
+                            .into_iter()
+                            .map(|(k, op)| (k.to_bytes(), op.map(|v| v.serialize())))
+                            .collect(),
+                        gas_used: output.get_gas_used(),
+                        events: output.get_events(),
+                    }),
+                    Err(_) => WorkerMessage::Error,
+                };
+                reply.write_to(&mut channel.0.lock().1)?;
+            }
+            CoordinatorMessage::ReadReply(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "read reply without read",
+                ))
+            }
+            CoordinatorMessage::Shutdown => return Ok(()),
+        }
+    }
+}
+/// worker side connection to coordinator
+struct Channel<R, W: Write>(Mutex<(BufReader<R>, BufWriter<W>)>);
+impl<K, V, R, W> RemoteRead<K, V> for Channel<R, W>
+where
+    K: WireBytes,
+    V: ValueBytes,
+    R: Read + Send,
+    W: Write + Send,
+{
+    fn read(&self, k: &K) -> ReadResult<V> {
+        let mut guard = self.0.lock();
+        let (input, output) = &mut *guard;
+        WorkerMessage::Read(k.to_bytes())
+            .write_to(output)
+            .expect("coordinator disconnected");
+        match CoordinatorMessage::read_from(input).expect("coordinator disconnected") {
+            CoordinatorMessage::ReadReply(reply) => match reply {
+                ReadReply::NotFound => ReadResult::NotFound,
+                ReadReply::Value(v) => ReadResult::Value(Arc::new(V::deserialize(&v))),
+                ReadReply::Deleted => ReadResult::Deleted,
+            },
+            message => panic!("unexpected message {:?} while reading", message),
+        }
+    }
+}
+/// worker process,talks with coordinator through its stdin/stdout
+struct Worker {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+impl Worker {
+    fn spawn(program: &OsStr, args: &[OsString]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = BufWriter::new(child.stdin.take().expect("stdin is piped"));
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+    fn send(&mut self, message: &CoordinatorMessage) -> io::Result<()> {
+        message.write_to(&mut self.stdin)
+    }
+    fn recv(&mut self) -> io::Result<WorkerMessage> {
+        WorkerMessage::read_from(&mut self.stdout)
+    }
+}
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // worker may be already dead,nothing to do with errors
+        let _ = self.send(&CoordinatorMessage::Shutdown);
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while let Ok(None) = self.child.try_wait() {
+            if Instant::now() >= deadline {
+                // hung worker
+                let _ = self.child.kill();
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.wait();
+    }
+}
+/// idle workers,taken by executors of coordinator,with the command to spawn more
+struct WorkerPool {
+    program: OsString,
+    args: Vec<OsString>,
+    idle: Mutex<Vec<Worker>>,
+}
+impl WorkerPool {
+    fn spawn(&self) -> io::Result<Worker> {
+        Worker::spawn(&self.program, &self.args)
+    }
+}
+/// vm used by executors of coordinator,which forwards transactions to the worker it holds
+struct RemoteVM<T> {
+    pool: Arc<WorkerPool>,
+    /// Mutex used to be `Sync`,`None` if worker is lost,then it's respawned on next transaction
+    worker: Mutex<Option<Worker>>,
+    phantom: PhantomData<T>,
+}
+/// output received from worker
+struct RemoteOutput<T>(Output, PhantomData<T>);
+impl<T> TransactionOutput for RemoteOutput<T>
+where
+    T: Transaction,
+    T::Key: WireBytes,
+{
+    type T = T;
+
+    fn get_write_set(&self) -> Vec<(T::Key, WriteOp<T::Value>)> {
+        self.0
+            .write_set
+            .iter()
+            .map(|(k, op)| {
+                let op = op.as_ref().map(|v| T::Value::deserialize(v));
+                (T::Key::from_bytes(k), op)
+            })
+            .collect()
+    }
+    fn get_gas_used(&self) -> u64 {
+        self.0.gas_used
+    }
+    fn get_events(&self) -> Vec<Event> {
+        self.0.events.clone()
+    }
+}
+impl<T> VM for RemoteVM<T>
+where
+    T: Transaction + WireBytes,
+    T::Key: WireBytes,
+{
+    type T = T;
+
+    type Output = RemoteOutput<T>;
+
+    type Error = io::Error;
+
+    type Parameter = Arc<WorkerPool>;
+
+    fn new(pool: Self::Parameter) -> Self {
+        let worker = pool.idle.lock().pop();
+        Self {
+            pool,
+            worker: Mutex::new(worker),
+            phantom: PhantomData,
+        }
+    }
+
+    fn execute_transaction(
+        &self,
+        txn: &Self::T,
+        view: &MVMemoryView<T::Key, T::Value>,
+    ) -> Result<Self::Output, Self::Error> {
+        let mut guard = self.worker.lock();
+        let mut retries = 0;
+        loop {
+            let worker = match &mut *guard {
+                Some(worker) => worker,
+                None => guard.insert(self.pool.spawn()?),
+            };
+            match Self::exchange(worker, txn, view) {
+                Ok(Some(output)) => return Ok(output),
+                Ok(None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "worker failed to execute transaction",
+                    ))
+                }
+                // broken connection,reads of view are cached,so retry sees the same values
+                Err(_) if retries < RESPAWN_RETRIES => {
+                    *guard = None;
+                    retries += 1;
+                }
+                Err(e) => {
+                    *guard = None;
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+impl<T> RemoteVM<T>
+where
+    T: Transaction + WireBytes,
+    T::Key: WireBytes,
+{
+    /// execute transaction on worker,serving its reads,`None` if vm of worker failed
+    fn exchange(
+        worker: &mut Worker,
+        txn: &T,
+        view: &MVMemoryView<T::Key, T::Value>,
+    ) -> io::Result<Option<RemoteOutput<T>>> {
+        worker.send(&CoordinatorMessage::Execute(view.txn_idx(), txn.to_bytes()))?;
+        loop {
+            match worker.recv()? {
+                WorkerMessage::Read(key) => {
+                    let reply = match view.read(&T::Key::from_bytes(&key)) {
+                        ReadResult::Value(v) => ReadReply::Value(v.serialize()),
+                        ReadResult::Deleted => ReadReply::Deleted,
+                        ReadResult::NotFound => ReadReply::NotFound,
+                    };
+                    worker.send(&CoordinatorMessage::ReadReply(reply))?;
+                }
+                WorkerMessage::Output(output) => {
+                    return Ok(Some(RemoteOutput(output, PhantomData)))
+                }
+                WorkerMessage::Error => return Ok(None),
+            }
+        }
+    }
+}
+impl<T> Drop for RemoteVM<T> {
+    fn drop(&mut self) {
+        // return worker for next block
+        if let Some(worker) = self.worker.lock().take() {
+            self.pool.idle.lock().push(worker);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    core::{Event, WriteOp},
+    types::{
+        codec::{read_bytes, read_u64, read_u8, write_bytes, write_u64, write_u8},
+        TxnIndex,
+    },
+};
+
+/// message sent from coordinator to worker
+#[derive(Debug, PartialEq, Eq)]
+pub enum CoordinatorMessage {
+    /// execute transaction (serialized) as given index
+    Execute(TxnIndex, Vec<u8>),
+    /// reply to `WorkerMessage::Read`
+    ReadReply(ReadReply),
+    /// no more transactions,worker should exit
+    Shutdown,
+}
+/// message sent from worker to coordinator
+#[derive(Debug, PartialEq, Eq)]
+pub enum WorkerMessage {
+    /// read key (serialized) from coordinator's mvmemory
+    Read(Vec<u8>),
+    /// output of executed transaction
+    Output(Output),
+    /// vm failed to execute transaction
+    Error,
+}
+/// output of executed transaction,with serialized write set
+#[derive(Debug, PartialEq, Eq)]
+pub struct Output {
+    /// write set (serialized) in write order
+    pub write_set: Vec<(Vec<u8>, WriteOp<Vec<u8>>)>,
+    /// gas used by transaction
+    pub gas_used: u64,
+    /// events in emission order
+    pub events: Vec<Event>,
+}
+/// value (serialized) of read key visible to transaction being executed
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReadReply {
+    /// key not written by lower transactions,worker reads it from storage
+    NotFound,
+    /// value written by lower transaction
+    Value(Vec<u8>),
+    /// key deleted by lower transaction
+    Deleted,
+}
+const EXECUTE: u8 = 0;
+const READ_REPLY: u8 = 1;
+const SHUTDOWN: u8 = 2;
+const READ: u8 = 3;
+const OUTPUT: u8 = 4;
+const ERROR: u8 = 5;
+const NOT_FOUND: u8 = 0;
+const VALUE: u8 = 1;
+const DELETED: u8 = 2;
+const CREATE: u8 = 0;
+const MODIFY: u8 = 1;
+const DELETE: u8 = 2;
+impl CoordinatorMessage {
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            Self::Execute(txn_idx, txn) => {
+                write_u8(w, EXECUTE)?;
+                write_u64(w, *txn_idx as u64)?;
+                write_bytes(w, txn)?;
+            }
+            Self::ReadReply(reply) => {
+                write_u8(w, READ_REPLY)?;
+                match reply {
+                    ReadReply::NotFound => write_u8(w, NOT_FOUND)?,
+                    ReadReply::Value(value) => {
+                        write_u8(w, VALUE)?;
+                        write_bytes(w, value)?;
+                    }
+                    ReadReply::Deleted => write_u8(w, DELETED)?,
+                }
+            }
+            Self::Shutdown => write_u8(w, SHUTDOWN)?,
+        }
+        w.flush()
+    }
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Self> {
+        match read_u8(r)? {
+            EXECUTE => Ok(Self::Execute(read_u64(r)? as TxnIndex, read_bytes(r)?)),
+            READ_REPLY => Ok(Self::ReadReply(match read_u8(r)? {
+                NOT_FOUND => ReadReply::NotFound,
+                VALUE => ReadReply::Value(read_bytes(r)?),
+                DELETED => ReadReply::Deleted,
+                tag => return Err(invalid_tag(tag)),
+            })),
+            SHUTDOWN => Ok(Self::Shutdown),
+            tag => Err(invalid_tag(tag)),
+        }
+    }
+}
+impl WorkerMessage {
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            Self::Read(key) => {
+                write_u8(w, READ)?;
+                write_bytes(w, key)?;
+            }
+            Self::Output(output) => {
+                write_u8(w, OUTPUT)?;
+                write_u64(w, output.write_set.len() as u64)?;
+                for (key, op) in &output.write_set {
+                    write_bytes(w, key)?;
+                    match op {
+                        WriteOp::Create(value) => {
+                            write_u8(w, CREATE)?;
+                            write_bytes(w, value)?;
+                        }
+                        WriteOp::Modify(value) => {
+                            write_u8(w, MODIFY)?;
+                            write_bytes(w, value)?;
+                        }
+                        WriteOp::Delete => write_u8(w, DELETE)?,
+                    }
+                }
+                write_u64(w, output.gas_used)?;
+                write_u64(w, output.events.len() as u64)?;
+                for event in &output.events {
+                    write_bytes(w, event.type_tag.as_bytes())?;
+                    write_bytes(w, &event.data)?;
+                }
+            }
+            Self::Error => write_u8(w, ERROR)?,
+        }
+        w.flush()
+    }
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Self> {
+        match read_u8(r)? {
+            READ => Ok(Self::Read(read_bytes(r)?)),
+            OUTPUT => {
+                let len = read_u64(r)? as usize;
+                let write_set = (0..len)
+                    .map(|_| Ok((read_bytes(r)?, read_write_op(r)?)))
+                    .collect::<io::Result<_>>()?;
+                let gas_used = read_u64(r)?;
+                let len = read_u64(r)? as usize;
+                let events = (0..len)
+                    .map(|_| {
+                        let type_tag = String::from_utf8(read_bytes(r)?)
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                        Ok(Event {
+                            type_tag,
+                            data: read_bytes(r)?,
+                        })
+                    })
+                    .collect::<io::Result<_>>()?;
+                Ok(Self::Output(Output {
+                    write_set,
+                    gas_used,
+                    events,
+                }))
+            }
+            ERROR => Ok(Self::Error),
+            tag => Err(invalid_tag(tag)),
+        }
+    }
+}
+fn read_write_op<R: Read>(r: &mut R) -> io::Result<WriteOp<Vec<u8>>> {
+    match read_u8(r)? {
+        CREATE => Ok(WriteOp::Create(read_bytes(r)?)),
+        MODIFY => Ok(WriteOp::Modify(read_bytes(r)?)),
+        DELETE => Ok(WriteOp::Delete),
+        tag => Err(invalid_tag(tag)),
+    }
+}
+fn invalid_tag(tag: u8) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid message tag {}", tag),
+    )
+}
+#[cfg(test)]
+mod tests {
+    use super::{Output, WorkerMessage};
+    use crate::core::{Event, WriteOp};
+
+    #[test]
+    fn output_roundtrip() {
+        let message = WorkerMessage::Output(Output {
+            write_set: vec![
+                (vec![0], WriteOp::Create(vec![1, 2])),
+                (vec![1], WriteOp::Modify(vec![3])),
+                (vec![2], WriteOp::Delete),
+            ],
+            gas_used: 42,
+            events: vec![Event {
+                type_tag: "transfer".to_string(),
+                data: vec![4, 5],
+            }],
+        });
+        let mut bytes = Vec::new();
+        message.write_to(&mut bytes).unwrap();
+        assert_eq!(
+            WorkerMessage::read_from(&mut bytes.as_slice()).unwrap(),
+            message
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use crossbeam::utils::CachePadded;
+use rayon::prelude::*;
+
+use crate::{
+    core::WriteOp,
+    types::{RwLock, TxnIndex, Version},
+};
+
+use super::{
+    mvmap::{latest_value, read_versions, Entry, MVMapError, MVMapOutput, Versions},
+    mvstore::{MVStore, MVStoreConfig},
+};
+
+/// key which can be mapped into a bounded integer space,used by `DenseMVMap`
+pub trait DenseKey: Sized {
+    /// index of key,must be smaller than the capacity of `DenseMVMap`
+    fn to_index(&self) -> usize;
+    /// recover key from index
+    fn from_index(index: usize) -> Self;
+}
+impl DenseKey for usize {
+    fn to_index(&self) -> usize {
+        *self
+    }
+    fn from_index(index: usize) -> Self {
+        index
+    }
+}
+impl DenseKey for u64 {
+    fn to_index(&self) -> usize {
+        *self as usize
+    }
+    fn from_index(index: usize) -> Self {
+        index as u64
+    }
+}
+impl DenseKey for u32 {
+    fn to_index(&self) -> usize {
+        *self as usize
+    }
+    fn from_index(index: usize) -> Self {
+        index as u32
+    }
+}
+/// array-indexed multi-version map for dense keys in `0..capacity`,
+/// which avoids hashing and the shard contention of dashmap.
+pub struct DenseMVMap<Value> {
+    /// `CachePadded` used to mitigate false sharing between adjacent keys.
+    slots: Vec<CachePadded<RwLock<Versions<Value>>>>,
+}
+impl<Value> DenseMVMap<Value> {
+    /// create an empty map for keys in `0..capacity`
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity)
+                .map(|_| CachePadded::new(RwLock::new(Versions::new())))
+                .collect(),
+        }
+    }
+    fn slot<Key: DenseKey>(&self, k: &Key) -> &RwLock<Versions<Value>> {
+        let index = k.to_index();
+        assert!(
+            index < self.slots.len(),
+            "dense key {} out of capacity {}",
+            index,
+            self.slots.len()
+        );
+        &self.slots[index]
+    }
+}
+impl<Key, Value> MVStore<Key, Value> for DenseMVMap<Value>
+where
+    Key: DenseKey + Send + Sync,
+    Value: Send + Sync,
+{
+    fn read(&self, k: &Key, txn_idx: TxnIndex) -> Result<MVMapOutput<Value>, MVMapError> {
+        read_versions(&self.slot(k).read(), txn_idx)
+    }
+    fn write(&self, k: Key, op: WriteOp<Value>, version: Version) {
+        let (txn_idx, incarnation) = version;
+        self.slot(&k)
+            .write()
+            .insert(txn_idx, CachePadded::new(Entry::new(incarnation, op)));
+    }
+    fn mark_estimate(&self, k: &Key, txn_idx: TxnIndex) {
+        self.slot(k)
+            .read()
+            .get(&txn_idx)
+            .expect("entry by txn idx must exist")
+            .mark_estimate();
+    }
+    fn delete(&self, k: &Key, txn_idx: TxnIndex) {
+        self.slot(k).write().remove(&txn_idx);
+    }
+    fn reset(&mut self) {
+        self.slots
+            .par_iter_mut()
+            .for_each(|slot| slot.get_mut().clear());
+    }
+    fn snapshot(&self, block_size: usize) -> Vec<(Key, Option<Arc<Value>>)> {
+        self.slots
+            .par_iter()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                latest_value(&slot.read(), block_size).map(|v| (Key::from_index(index), v))
+            })
+            .collect()
+    }
+}
+/// config of `DenseMVMap`
+#[derive(Clone)]
+pub struct DenseMVMapConfig {
+    /// keys must be in `0..capacity`
+    pub capacity: usize,
+}
+impl<Key, Value> MVStoreConfig<Key, Value> for DenseMVMapConfig
+where
+    Key: DenseKey + Send + Sync,
+    Value: Send + Sync,
+{
+    type Store = DenseMVMap<Value>;
+
+    fn build(&self) -> Self::Store {
+        DenseMVMap::new(self.capacity)
+    }
+}
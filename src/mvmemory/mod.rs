@@ -1,46 +1,72 @@
-use std::{fmt::Debug, hash::Hash, sync::Arc};
+use std::{collections::HashMap, fmt::Debug, hash::Hash, marker::PhantomData, sync::Arc};
 
 use crate::{
-    core::ValueBytes,
+    contention::ContentionReport,
+    core::{Event, WriteOp},
     scheduler::Scheduler,
     types::Mutex,
     types::{Incarnation, TxnIndex, Version},
 };
 use arc_swap::ArcSwapOption;
 use crossbeam::utils::CachePadded;
-use rayon::prelude::*;
 
-use self::mvmap::{EntryCell, MVMap, MVMapError, MVMapOutput};
+pub use self::{
+    dense::{DenseKey, DenseMVMap, DenseMVMapConfig},
+    mvmap::{MVMap, MVMapConfig, MVMapError, MVMapOutput},
+    mvstore::{MVStore, MVStoreConfig},
+};
 
+/// dense mvmap
+mod dense;
 /// mvmap
 mod mvmap;
+/// mvstore traits
+mod mvstore;
 /// read set captured from mvmemory view
 type ResdSet<Key> = Vec<ReadDescriptor<Key>>;
+/// gas used and events emitted by incarnation
+pub type Receipt = (u64, Vec<Event>);
 /// mvmemory
-pub struct MVMemory<Key, Value> {
+pub struct MVMemory<Key, Value, S> {
     block_size: usize,
-    data: MVMap<Key, Value>,
+    data: S,
     /// ArcSwapOption used to read/write atomically and efficiently (RCU)
     last_written_locations: Vec<CachePadded<ArcSwapOption<Vec<Key>>>>,
     /// ArcSwapOption used to read/write atomically and efficiently (RCU)
     last_read_set: Vec<CachePadded<ArcSwapOption<ResdSet<Key>>>>,
+    /// ArcSwapOption used to read/write atomically and efficiently (RCU)
+    last_receipts: Vec<CachePadded<ArcSwapOption<Receipt>>>,
+    /// error of the last incarnation of each transaction,if vm failed to execute it
+    last_errors: Vec<CachePadded<ArcSwapOption<String>>>,
+    /// invalid reads of aborted incarnations with their writers,used by contention report
+    aborts: Mutex<Vec<(Key, TxnIndex)>>,
+    phantom: PhantomData<Value>,
 }
 /// public methods used by other components
-impl<Key, Value> MVMemory<Key, Value>
+impl<Key, Value, S> MVMemory<Key, Value, S>
 where
     Key: Eq + Hash + Send + Sync + Clone + Debug,
-    Value: Send + Sync + ValueBytes,
+    Value: Clone + Send + Sync,
+    S: MVStore<Key, Value>,
 {
-    pub fn new(block_size: usize) -> Self {
+    pub fn new(block_size: usize, data: S) -> Self {
         Self {
             block_size,
-            data: MVMap::new(),
+            data,
             last_written_locations: (0..block_size)
                 .map(|_| CachePadded::new(ArcSwapOption::empty()))
                 .collect(),
             last_read_set: (0..block_size)
                 .map(|_| CachePadded::new(ArcSwapOption::empty()))
                 .collect(),
+            last_receipts: (0..block_size)
+                .map(|_| CachePadded::new(ArcSwapOption::empty()))
+                .collect(),
+            last_errors: (0..block_size)
+                .map(|_| CachePadded::new(ArcSwapOption::empty()))
+                .collect(),
+            aborts: Mutex::new(Vec::new()),
+            phantom: PhantomData,
         }
     }
     pub fn read(&self, k: &Key, txn_idx: TxnIndex) -> Result<MVMapOutput<Value>, MVMapError> {
@@ -50,15 +76,39 @@ where
         &self,
         version: Version,
         read_set: Vec<ReadDescriptor<Key>>,
-        write_set: Vec<(Key, Value)>,
+        write_set: Vec<(Key, WriteOp<Value>)>,
+        receipt: Receipt,
     ) -> bool {
         let (txn_idx, incarnation) = version;
         let new_locations = write_set.iter().map(|(key, _)| key).cloned().collect();
         self.apply_write_set(txn_idx, incarnation, write_set);
         let wrote_new_location = self.rcu_update_written_locations(txn_idx, new_locations);
         self.last_read_set[txn_idx].store(Some(Arc::new(read_set)));
+        self.last_receipts[txn_idx].store(Some(Arc::new(receipt)));
+        self.last_errors[txn_idx].store(None);
+        wrote_new_location
+    }
+    /// record incarnation which vm failed to execute,as if it read `read_set` and wrote nothing.
+    ///
+    /// incarnation may have read inconsistent values,so error is only its output,
+    /// which fails block if it's still there when all transactions are committed,see `first_error`
+    pub fn record_error(
+        &self,
+        version: Version,
+        read_set: Vec<ReadDescriptor<Key>>,
+        error: String,
+    ) -> bool {
+        let wrote_new_location = self.record(version, read_set, vec![], (0, vec![]));
+        self.last_errors[version.0].store(Some(Arc::new(error)));
         wrote_new_location
     }
+    /// the first transaction whose last incarnation failed to execute,with its error
+    pub fn first_error(&self) -> Option<(TxnIndex, String)> {
+        self.last_errors
+            .iter()
+            .enumerate()
+            .find_map(|(txn_idx, error)| error.load_full().map(|error| (txn_idx, (*error).clone())))
+    }
     pub fn convert_writes_to_estimates(&self, txn_idx: TxnIndex) {
         let prev_locations = self.last_written_locations[txn_idx].load_full();
         if let Some(prev_locations) = prev_locations {
@@ -67,52 +117,213 @@ where
             }
         }
     }
-    pub fn validate_read_set(&self, txn_idx: TxnIndex) -> bool {
+    /// validate read set of the last incarnation of transaction,
+    /// return the first invalid read with the transaction that wrote the new (or removed the old) version of it
+    pub fn validate_read_set(&self, txn_idx: TxnIndex) -> Result<(), (Key, TxnIndex)> {
         let prior_reads = self.last_read_set[txn_idx].load_full();
         if let Some(prior_reads) = prior_reads {
-            return prior_reads
+            let conflict = prior_reads
                 .iter()
-                .all(|r| match self.read(r.key(), txn_idx) {
-                    Ok(MVMapOutput::Version(version, _)) => r.validate_version(version),
-                    Err(MVMapError::ReadError(_)) => false,
-                    Err(MVMapError::NotFound) => r.validate_storage(),
+                .find_map(|r| match self.read(r.key(), txn_idx) {
+                    Ok(output) => {
+                        let version = output.version();
+                        (!r.validate_version(version)).then(|| (r.key.clone(), version.0))
+                    }
+                    Err(MVMapError::ReadError(blocking_txn_idx)) => {
+                        Some((r.key.clone(), blocking_txn_idx))
+                    }
+                    Err(MVMapError::NotFound) => match r.read_type {
+                        ReadType::Version((writer, _)) => Some((r.key.clone(), writer)),
+                        ReadType::Storage => None,
+                    },
                 });
+            if let Some(conflict) = conflict {
+                return Err(conflict);
+            }
         }
-        true
+        Ok(())
+    }
+    /// record abort caused by failed validation,see `validate_read_set`
+    pub fn record_abort(&self, key: Key, writer: TxnIndex) {
+        self.aborts.lock().push((key, writer));
+    }
+    /// ranking of keys and writers by aborts recorded so far
+    pub fn contention(&self) -> ContentionReport<Key> {
+        ContentionReport::new(&self.aborts.lock())
     }
     pub fn snapshot(self) -> Vec<(Key, Option<Value>)> {
-        let map = self.data.inner;
-        map.into_par_iter()
-            .filter(|(_, v)| !v.is_empty())
-            .map(|(location, versions)| {
-                if let Some((_, enrty)) = versions.range(0..self.block_size).next_back() {
-                    match &enrty.cell {
-                        // TODO: make sense?
-                        EntryCell::Write(_, v) => match v.serialize() {
-                            Some(bytes) => (location, Some(Value::deserialize(&bytes))),
-                            None => (location, None),
-                        },
+        let block_size = self.block_size;
+        self.data
+            .into_snapshot(block_size)
+            .into_iter()
+            .map(|(location, v)| (location, v.map(Self::final_value)))
+            .collect()
+    }
+    /// same as `snapshot`,but keep mvmemory for reuse
+    pub fn snapshot_cloned(&self) -> Vec<(Key, Option<Value>)> {
+        self.data
+            .snapshot(self.block_size)
+            .into_iter()
+            .map(|(location, v)| (location, v.map(Self::final_value)))
+            .collect()
+    }
+    /// write set of the last incarnation of transaction,in write order,with kind of each write
+    pub fn write_set(&self, txn_idx: TxnIndex) -> Vec<(Key, WriteOp<Value>)> {
+        let locations = self.last_written_locations[txn_idx].load_full();
+        locations.map_or_else(Vec::new, |locations| {
+            locations
+                .iter()
+                .map(|location| match self.data.read(location, txn_idx + 1) {
+                    Ok(output) if output.version().0 == txn_idx => {
+                        (location.clone(), output.op.map(Self::final_value))
                     }
-                } else {
-                    unreachable!()
+                    _ => unreachable!("location written by transaction must be readable"),
+                })
+                .collect()
+        })
+    }
+    /// same as `write_set`,but with value of each key before transaction,
+    /// `None` if transaction reads it from storage,`Some(None)` if it's deleted by lower transaction
+    #[allow(clippy::type_complexity)]
+    pub fn changes(&self, txn_idx: TxnIndex) -> Vec<(Key, Option<Option<Value>>, WriteOp<Value>)> {
+        self.write_set(txn_idx)
+            .into_iter()
+            .map(|(location, after)| {
+                let before = match self.data.read(&location, txn_idx) {
+                    Ok(output) => Some(output.value().map(Self::final_value)),
+                    Err(MVMapError::NotFound) => None,
+                    Err(MVMapError::ReadError(_)) => {
+                        unreachable!("committed transaction should not read estimates")
+                    }
+                };
+                (location, before, after)
+            })
+            .collect()
+    }
+    /// first transaction writing each key and position of key in its write set,used to order snapshot
+    pub fn first_writes(&self) -> HashMap<Key, (TxnIndex, usize)> {
+        let mut first_writes = HashMap::new();
+        for (txn_idx, locations) in self.last_written_locations.iter().enumerate() {
+            if let Some(locations) = locations.load_full() {
+                for (position, location) in locations.iter().enumerate() {
+                    first_writes
+                        .entry(location.clone())
+                        .or_insert((txn_idx, position));
                 }
+            }
+        }
+        first_writes
+    }
+    /// keys written by the last incarnation of each transaction
+    pub fn written_keys(&self) -> Vec<Key> {
+        self.last_written_locations
+            .iter()
+            .filter_map(|locations| locations.load_full())
+            .flat_map(|locations| locations.iter().cloned().collect::<Vec<_>>())
+            .collect()
+    }
+    /// keys read by the last incarnation of each transaction
+    pub fn read_keys(&self) -> Vec<Key> {
+        self.last_read_set
+            .iter()
+            .filter_map(|read_set| read_set.load_full())
+            .flat_map(|read_set| read_set.iter().map(|r| r.key().clone()).collect::<Vec<_>>())
+            .collect()
+    }
+    /// read set of the last incarnation of transaction,each key with the transaction it was read from,
+    /// `None` for storage,incarnations are dropped since they depend on scheduling
+    pub fn read_set(&self, txn_idx: TxnIndex) -> Vec<(Key, Option<TxnIndex>)> {
+        self.last_read_set[txn_idx]
+            .load_full()
+            .map_or_else(Vec::new, |read_set| {
+                read_set
+                    .iter()
+                    .map(|r| {
+                        let source = match r.read_type {
+                            ReadType::Version((txn_idx, _)) => Some(txn_idx),
+                            ReadType::Storage => None,
+                        };
+                        (r.key.clone(), source)
+                    })
+                    .collect()
+            })
+    }
+    /// gas used and events of the last incarnation of transaction,`(0, [])` if it's not executed
+    pub fn receipt(&self, txn_idx: TxnIndex) -> Receipt {
+        self.last_receipts[txn_idx]
+            .load_full()
+            .map_or_else(|| (0, vec![]), |receipt| (*receipt).clone())
+    }
+    /// total gas used by the last incarnation of each transaction
+    pub fn gas_used(&self) -> u64 {
+        self.last_receipts
+            .iter()
+            .filter_map(|receipt| receipt.load_full())
+            .map(|receipt| receipt.0)
+            .sum()
+    }
+    /// events emitted by the last incarnation of each transaction,in block order then emission order
+    pub fn events(&self) -> Vec<(TxnIndex, Event)> {
+        self.last_receipts
+            .iter()
+            .enumerate()
+            .filter_map(|(txn_idx, receipt)| receipt.load_full().map(|receipt| (txn_idx, receipt)))
+            .flat_map(|(txn_idx, receipt)| {
+                receipt
+                    .1
+                    .iter()
+                    .map(|event| (txn_idx, event.clone()))
+                    .collect::<Vec<_>>()
             })
             .collect()
     }
+    /// resize per-transaction slots for next block,mvmemory must be cleared
+    pub fn resize(&mut self, block_size: usize) {
+        self.block_size = block_size;
+        self.last_written_locations
+            .resize_with(block_size, || CachePadded::new(ArcSwapOption::empty()));
+        self.last_read_set
+            .resize_with(block_size, || CachePadded::new(ArcSwapOption::empty()));
+        self.last_receipts
+            .resize_with(block_size, || CachePadded::new(ArcSwapOption::empty()));
+        self.last_errors
+            .resize_with(block_size, || CachePadded::new(ArcSwapOption::empty()));
+    }
+    /// clear all state of last block,keep allocations for reuse
+    pub fn clear(&mut self) {
+        self.data.reset();
+        self.last_written_locations
+            .iter()
+            .for_each(|locations| locations.store(None));
+        self.last_read_set
+            .iter()
+            .for_each(|read_set| read_set.store(None));
+        self.last_receipts
+            .iter()
+            .for_each(|receipt| receipt.store(None));
+        self.last_errors.iter().for_each(|error| error.store(None));
+        self.aborts.lock().clear();
+    }
 }
 /// private methods used by mvmemory itself
-impl<Key, Value> MVMemory<Key, Value>
+impl<Key, Value, S> MVMemory<Key, Value, S>
 where
     Key: Eq + Hash,
+    Value: Clone,
+    S: MVStore<Key, Value>,
 {
+    /// owned value of version,cloned only if it's still shared (e.g. by a view)
+    fn final_value(v: Arc<Value>) -> Value {
+        Arc::try_unwrap(v).unwrap_or_else(|v| (*v).clone())
+    }
     fn apply_write_set(
         &self,
         txn_idx: TxnIndex,
         incarnation: Incarnation,
-        write_set: Vec<(Key, Value)>,
+        write_set: Vec<(Key, WriteOp<Value>)>,
     ) {
-        for (key, value) in write_set {
-            self.data.write(key, value, (txn_idx, incarnation));
+        for (key, op) in write_set {
+            self.data.write(key, op, (txn_idx, incarnation));
         }
     }
     fn rcu_update_written_locations(&self, txn_idx: TxnIndex, new_locations: Vec<Key>) -> bool {
@@ -149,18 +360,6 @@ impl<K> ReadDescriptor<K>
 where
     K: Debug,
 {
-    pub fn new_version(key: K, version: Version) -> Self {
-        Self {
-            key,
-            read_type: ReadType::Version(version),
-        }
-    }
-    pub fn new_storage(key: K) -> Self {
-        Self {
-            key,
-            read_type: ReadType::Storage,
-        }
-    }
     pub fn key(&self) -> &K {
         &self.key
     }
@@ -171,67 +370,176 @@ where
         self.read_type == ReadType::Storage
     }
 }
+/// private methods used by mvmemory view itself
+impl<'a, K, V> MVMemoryView<'a, K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    fn read_local(&self, k: &K, data: &dyn MVStore<K, V>, scheduler: &Scheduler) -> ReadResult<V> {
+        loop {
+            // scheduler halted by watchdog,output of this incarnation will be discarded
+            if scheduler.halted() {
+                return ReadResult::NotFound;
+            }
+            match data.read(k, self.txn_idx) {
+                Ok(MVMapOutput { version, op }) => {
+                    let result = match op.into_value() {
+                        Some(v) => ReadResult::Value(v),
+                        None => ReadResult::Deleted,
+                    };
+                    self.capture(k, Some(ReadType::Version(version)), result.clone());
+                    return result;
+                }
+                Err(MVMapError::NotFound) => {
+                    self.capture(k, Some(ReadType::Storage), ReadResult::NotFound);
+                    return ReadResult::NotFound;
+                }
+                Err(MVMapError::ReadError(blocking_txn_idx)) => {
+                    match scheduler.wait_for_dependency(self.txn_idx, blocking_txn_idx) {
+                        Some(condvar) => scheduler.wait(condvar),
+                        None => continue,
+                    }
+                }
+            }
+        }
+    }
+    fn capture(&self, k: &K, read_type: Option<ReadType>, value: ReadResult<V>) {
+        self.captured_reads
+            .lock()
+            .insert(k.clone(), CapturedRead { read_type, value });
+    }
+}
 /// read result from mvmemory view
 pub enum ReadResult<V> {
+    /// value written by lower transaction
     Value(Arc<V>),
+    /// key deleted by lower transaction,it must not be read from storage
+    Deleted,
+    /// key not written by lower transactions,read it from storage
     NotFound,
 }
-/// mvmemory view,mvmemory used to read,scheduler used to add dependency
+/// `V: Clone` is not needed to clone `Arc<V>`
+impl<V> Clone for ReadResult<V> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Value(v) => Self::Value(v.clone()),
+            Self::Deleted => Self::Deleted,
+            Self::NotFound => Self::NotFound,
+        }
+    }
+}
+/// read captured by mvmemory view,value is cached to serve repeated reads of the same key
+struct CapturedRead<V> {
+    /// `None` for remote reads,whose read type is tracked by coordinator
+    read_type: Option<ReadType>,
+    value: ReadResult<V>,
+}
+/// reads served by another process,used by multi-process workers
+pub trait RemoteRead<K, V>: Sync {
+    /// read value of key visible to transaction being executed
+    fn read(&self, k: &K) -> ReadResult<V>;
+}
+/// where mvmemory view reads from
+enum ViewSource<'a, K, V> {
+    /// trait object used to keep view (and `VM`) independent of mvstore type
+    Local {
+        data: &'a dyn MVStore<K, V>,
+        scheduler: &'a Scheduler,
+    },
+    Remote(&'a dyn RemoteRead<K, V>),
+}
+/// mvmemory view,mvstore used to read,scheduler used to add dependency
+///
+/// view is created per incarnation,so captured reads are also a per-incarnation read cache,
+/// and the read set taken from view is deduplicated by key.
 pub struct MVMemoryView<'a, K, V> {
     txn_idx: TxnIndex,
-    mvmemory: &'a MVMemory<K, V>,
-    scheduler: &'a Scheduler,
+    source: ViewSource<'a, K, V>,
     /// Mutex used to be `Sync`
-    captured_reads: Mutex<Vec<ReadDescriptor<K>>>,
+    captured_reads: Mutex<HashMap<K, CapturedRead<V>>>,
 }
 /// public methods used by executor
 impl<'a, K, V> MVMemoryView<'a, K, V>
 where
     K: Eq + Hash + Send + Sync + Clone + Debug,
-    V: Send + Sync + ValueBytes,
+    V: Send + Sync,
 {
-    pub fn new(txn_idx: TxnIndex, mvmemory: &'a MVMemory<K, V>, scheduler: &'a Scheduler) -> Self {
+    pub fn new<S: MVStore<K, V>>(
+        txn_idx: TxnIndex,
+        mvmemory: &'a MVMemory<K, V, S>,
+        scheduler: &'a Scheduler,
+    ) -> Self {
+        Self {
+            txn_idx,
+            source: ViewSource::Local {
+                data: &mvmemory.data,
+                scheduler,
+            },
+            captured_reads: Mutex::new(HashMap::new()),
+        }
+    }
+    /// view whose reads are served by coordinator,used by multi-process workers
+    pub fn remote(txn_idx: TxnIndex, remote: &'a dyn RemoteRead<K, V>) -> Self {
         Self {
             txn_idx,
-            mvmemory,
-            scheduler,
-            captured_reads: Mutex::new(Vec::new()),
+            source: ViewSource::Remote(remote),
+            captured_reads: Mutex::new(HashMap::new()),
         }
     }
     pub fn read(&self, k: &K) -> ReadResult<V> {
-        loop {
-            match self.mvmemory.read(k, self.txn_idx) {
-                Ok(MVMapOutput::Version(version, v)) => {
-                    self.captured_reads
-                        .lock()
-                        .push(ReadDescriptor::new_version(k.clone(), version));
-                    return ReadResult::Value(v);
-                }
-                Err(MVMapError::NotFound) => {
-                    self.captured_reads
-                        .lock()
-                        .push(ReadDescriptor::new_storage(k.clone()));
-                    return ReadResult::NotFound;
-                }
-                Err(MVMapError::ReadError(blocking_txn_idx)) => {
-                    match self
-                        .scheduler
-                        .wait_for_dependency(self.txn_idx, blocking_txn_idx)
-                    {
-                        Some(condvar) => {
-                            condvar.wait();
-                        }
-                        None => continue,
-                    }
-                }
+        if let Some(captured) = self.captured_reads.lock().get(k) {
+            return captured.value.clone();
+        }
+        match self.source {
+            ViewSource::Local { data, scheduler } => self.read_local(k, data, scheduler),
+            ViewSource::Remote(remote) => {
+                let value = remote.read(k);
+                self.capture(k, None, value.clone());
+                value
             }
         }
     }
     pub fn take_read_set(&mut self) -> Vec<ReadDescriptor<K>> {
-        let mut read_set = self.captured_reads.lock();
-        std::mem::take(&mut read_set)
+        let mut captured_reads = self.captured_reads.lock();
+        captured_reads
+            .drain()
+            .filter_map(|(key, captured)| {
+                captured
+                    .read_type
+                    .map(|read_type| ReadDescriptor { key, read_type })
+            })
+            .collect()
     }
     pub fn txn_idx(&self) -> TxnIndex {
         self.txn_idx
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::{MVMapConfig, MVMemory, MVMemoryView, MVStoreConfig, ReadResult, ReadType};
+    use crate::{core::WriteOp, scheduler::Scheduler};
+
+    #[test]
+    fn view_cache() {
+        let mvmemory = MVMemory::new(2, MVMapConfig::default().build());
+        let scheduler = Scheduler::new(2);
+        let value = |result: ReadResult<usize>| match result {
+            ReadResult::Value(v) => Some(*v),
+            _ => None,
+        };
+        mvmemory.record((0, 0), vec![], vec![(1, WriteOp::Modify(10))], (0, vec![]));
+        let mut view = MVMemoryView::new(1, &mvmemory, &scheduler);
+        assert_eq!(value(view.read(&1)), Some(10));
+        // txn 0 is re-executed,the incarnation reading through view keeps what it has read
+        mvmemory.record((0, 1), vec![], vec![(1, WriteOp::Modify(20))], (0, vec![]));
+        assert_eq!(value(view.read(&1)), Some(10));
+        assert!(matches!(view.read(&2), ReadResult::NotFound));
+        assert!(matches!(view.read(&2), ReadResult::NotFound));
+        let mut read_set = view.take_read_set();
+        read_set.sort_by_key(|read| read.key);
+        assert_eq!(read_set.len(), 2);
+        assert_eq!(read_set[0].read_type, ReadType::Version((0, 0)));
+        assert_eq!(read_set[1].read_type, ReadType::Storage);
+        assert!(view.take_read_set().is_empty());
+    }
+}
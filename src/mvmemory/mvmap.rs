@@ -1,10 +1,20 @@
-use std::{collections::BTreeMap, hash::Hash, sync::Arc};
+use std::{
+    collections::{hash_map::RandomState, BTreeMap},
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
 
 use crossbeam::utils::CachePadded;
 use dashmap::DashMap;
+use rayon::prelude::*;
 use thiserror::Error;
 
-use crate::types::{AtomicBool, Incarnation, TxnIndex, Version};
+use crate::{
+    core::WriteOp,
+    types::{AtomicBool, Incarnation, TxnIndex, Version},
+};
+
+use super::mvstore::{MVStore, MVStoreConfig};
 
 /// design comes from `aptos-core`
 pub struct Entry<Value> {
@@ -14,15 +24,20 @@ pub struct Entry<Value> {
     pub cell: EntryCell<Value>,
 }
 /// entry cell uses Arc for shared ownership and avoids unnecessary data clones.
-pub enum EntryCell<Value> {
-    /// write record
-    Write(Incarnation, Arc<Value>),
+pub struct EntryCell<Value> {
+    /// incarnation of writer
+    pub incarnation: Incarnation,
+    /// write of incarnation,kind of write is kept,deletion is a version too
+    pub op: WriteOp<Arc<Value>>,
 }
 impl<Value> Entry<Value> {
-    pub fn new(incarnation: Incarnation, v: Arc<Value>) -> Self {
+    pub fn new(incarnation: Incarnation, op: WriteOp<Value>) -> Self {
         Self {
             flag: AtomicBool::new(false),
-            cell: EntryCell::Write(incarnation, v),
+            cell: EntryCell {
+                incarnation,
+                op: op.map(Arc::new),
+            },
         }
     }
     pub fn is_estimate(&self) -> bool {
@@ -32,71 +47,161 @@ impl<Value> Entry<Value> {
         self.flag.store(true);
     }
 }
-/// read output from mvmap
-pub enum MVMapOutput<Value> {
-    Version(Version, Arc<Value>),
+/// versions of single key,`BTreeMap` used to `read` faster,`CachePadded` used to mitigate false sharing.
+pub type Versions<Value> = BTreeMap<TxnIndex, CachePadded<Entry<Value>>>;
+/// read output from mvmap,which is the write of the highest transaction below reader
+pub struct MVMapOutput<Value> {
+    /// version of read entry
+    pub version: Version,
+    /// write of read entry,`WriteOp::Delete` for deletion
+    pub op: WriteOp<Arc<Value>>,
+}
+impl<Value> MVMapOutput<Value> {
+    /// version of read entry
+    pub fn version(&self) -> Version {
+        self.version
+    }
+    /// value of read entry,`None` for deletion
+    pub fn value(self) -> Option<Arc<Value>> {
+        self.op.into_value()
+    }
 }
 /// error from mvmap
 #[derive(Debug, Error)]
 pub enum MVMapError {
+    /// no transaction below reader wrote the key
     #[error("NotFound")]
     NotFound,
+    /// the highest transaction below reader wrote an estimate,reader depends on it
     #[error("ReadError:{0}")]
     ReadError(TxnIndex),
 }
+/// read the highest version below `txn_idx`,shared by mvstore implementations
+pub fn read_versions<Value>(
+    versions: &Versions<Value>,
+    txn_idx: TxnIndex,
+) -> Result<MVMapOutput<Value>, MVMapError> {
+    match versions.range(0..txn_idx).next_back() {
+        Some((idx, entry)) => {
+            if entry.is_estimate() {
+                Err(MVMapError::ReadError(*idx))
+            } else {
+                Ok(MVMapOutput {
+                    version: (*idx, entry.cell.incarnation),
+                    op: entry.cell.op.clone(),
+                })
+            }
+        }
+        None => Err(MVMapError::NotFound),
+    }
+}
+/// latest value below `block_size`,`Some(None)` if it's deleted,shared by mvstore implementations
+pub fn latest_value<Value>(
+    versions: &Versions<Value>,
+    block_size: usize,
+) -> Option<Option<Arc<Value>>> {
+    versions
+        .range(0..block_size)
+        .next_back()
+        .map(|(_, entry)| entry.cell.op.clone().into_value())
+}
 /// multi-version hashmap used by block-stm
-pub struct MVMap<Key, Value> {
-    /// `BTreeMap` used to `read` faster,`CachePadded` used to mitigate false sharing.
-    pub inner: DashMap<Key, BTreeMap<TxnIndex, CachePadded<Entry<Value>>>>,
+pub struct MVMap<Key, Value, H = RandomState> {
+    inner: DashMap<Key, Versions<Value>, H>,
 }
 /// public methods used by mvmemory
-impl<Key, Value> MVMap<Key, Value>
+impl<Key, Value, H> MVMap<Key, Value, H>
 where
     Key: Eq + Hash,
+    H: BuildHasher + Clone,
 {
-    pub fn new() -> Self {
+    /// create an empty mvmap with given hasher and shard amount (power of two)
+    pub fn with_hasher_and_shard_amount(hasher: H, shard_amount: usize) -> Self {
         Self {
-            inner: DashMap::new(),
+            inner: DashMap::with_hasher_and_shard_amount(hasher, shard_amount),
         }
     }
-    pub fn read(&self, k: &Key, txn_idx: TxnIndex) -> Result<MVMapOutput<Value>, MVMapError> {
+}
+impl<Key, Value, H> MVStore<Key, Value> for MVMap<Key, Value, H>
+where
+    Key: Eq + Hash + Clone + Send + Sync,
+    Value: Send + Sync,
+    H: BuildHasher + Clone + Send + Sync,
+{
+    fn read(&self, k: &Key, txn_idx: TxnIndex) -> Result<MVMapOutput<Value>, MVMapError> {
         match self.inner.get(k) {
-            Some(map) => {
-                let mut iter = map.range(0..txn_idx);
-                match iter.next_back() {
-                    Some((idx, entry)) => {
-                        if entry.is_estimate() {
-                            Err(MVMapError::ReadError(*idx))
-                        } else {
-                            match &entry.cell {
-                                EntryCell::Write(incarnation, v) => {
-                                    Ok(MVMapOutput::Version((*idx, *incarnation), v.clone()))
-                                }
-                            }
-                        }
-                    }
-                    None => Err(MVMapError::NotFound),
-                }
-            }
+            Some(versions) => read_versions(&versions, txn_idx),
             None => Err(MVMapError::NotFound),
         }
     }
-    pub fn write(&self, k: Key, v: Value, version: Version) {
+    fn write(&self, k: Key, op: WriteOp<Value>, version: Version) {
         let (txn_idx, incarnation) = version;
-        let mut map = self.inner.entry(k).or_insert(BTreeMap::new());
-        map.insert(
-            txn_idx,
-            CachePadded::new(Entry::new(incarnation, Arc::new(v))),
-        );
-    }
-    pub fn mark_estimate(&self, k: &Key, txn_idx: TxnIndex) {
-        let map = self.inner.get(k).expect("key must exist");
-        map.get(&txn_idx)
+        let mut versions = self.inner.entry(k).or_default();
+        versions.insert(txn_idx, CachePadded::new(Entry::new(incarnation, op)));
+    }
+    fn mark_estimate(&self, k: &Key, txn_idx: TxnIndex) {
+        let versions = self.inner.get(k).expect("key must exist");
+        versions
+            .get(&txn_idx)
             .expect("entry by txn idx must exist")
             .mark_estimate();
     }
-    pub fn delete(&self, k: &Key, txn_idx: TxnIndex) {
-        let mut map = self.inner.get_mut(k).expect("key must exist");
-        map.remove(&txn_idx);
+    fn delete(&self, k: &Key, txn_idx: TxnIndex) {
+        let mut versions = self.inner.get_mut(k).expect("key must exist");
+        versions.remove(&txn_idx);
+    }
+    /// keys written in last block keep their (emptied) slots for reuse,
+    /// other keys are removed to bound the memory held across blocks.
+    ///
+    /// entries and values are freed with versions,only slots of keys are reused.
+    fn reset(&mut self) {
+        self.inner.retain(|_, versions| {
+            let written = !versions.is_empty();
+            versions.clear();
+            written
+        });
+    }
+    fn snapshot(&self, block_size: usize) -> Vec<(Key, Option<Arc<Value>>)> {
+        self.inner
+            .par_iter()
+            .filter_map(|versions| {
+                latest_value(&versions, block_size).map(|v| (versions.key().clone(), v))
+            })
+            .collect()
+    }
+    fn into_snapshot(self, block_size: usize) -> Vec<(Key, Option<Arc<Value>>)> {
+        self.inner
+            .into_par_iter()
+            .filter_map(|(k, versions)| latest_value(&versions, block_size).map(|v| (k, v)))
+            .collect()
+    }
+}
+/// config of `MVMap`
+#[derive(Clone)]
+pub struct MVMapConfig<H = RandomState> {
+    /// hasher used by dashmap
+    pub hasher: H,
+    /// shard amount of dashmap,must be a power of two bigger than 1
+    pub shard_amount: usize,
+}
+impl Default for MVMapConfig {
+    /// same as dashmap default
+    fn default() -> Self {
+        Self {
+            hasher: RandomState::default(),
+            shard_amount: (num_cpus::get() * 4).next_power_of_two(),
+        }
+    }
+}
+impl<Key, Value, H> MVStoreConfig<Key, Value> for MVMapConfig<H>
+where
+    Key: Eq + Hash + Clone + Send + Sync,
+    Value: Send + Sync,
+    H: BuildHasher + Clone + Send + Sync,
+{
+    type Store = MVMap<Key, Value, H>;
+
+    fn build(&self) -> Self::Store {
+        MVMap::with_hasher_and_shard_amount(self.hasher.clone(), self.shard_amount)
     }
 }
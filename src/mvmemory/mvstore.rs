@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use crate::{
+    core::WriteOp,
+    types::{TxnIndex, Version},
+};
+
+use super::mvmap::{MVMapError, MVMapOutput};
+
+/// multi-version store used by mvmemory,which keeps the versions written by each transaction for every key
+///
+/// `Send + Sync` needed by rayon
+pub trait MVStore<Key, Value>: Send + Sync {
+    /// read the version written by the highest transaction below `txn_idx`
+    fn read(&self, k: &Key, txn_idx: TxnIndex) -> Result<MVMapOutput<Value>, MVMapError>;
+    /// write `k` with given version,deletion is kept as a version too
+    fn write(&self, k: Key, op: WriteOp<Value>, version: Version);
+    /// mark the entry of `k` written by `txn_idx` as estimate
+    fn mark_estimate(&self, k: &Key, txn_idx: TxnIndex);
+    /// delete the entry of `k` written by `txn_idx`
+    fn delete(&self, k: &Key, txn_idx: TxnIndex);
+    /// drop all versions,allocations may be kept for reuse
+    fn reset(&mut self);
+    /// latest value of every written key,written by transactions below `block_size`,`None` indicates deletion
+    fn snapshot(&self, block_size: usize) -> Vec<(Key, Option<Arc<Value>>)>;
+    /// same as `snapshot`,but consume store to avoid key clones
+    fn into_snapshot(self, block_size: usize) -> Vec<(Key, Option<Arc<Value>>)>
+    where
+        Self: Sized,
+    {
+        self.snapshot(block_size)
+    }
+}
+/// config used to create multi-version store
+pub trait MVStoreConfig<Key, Value>: Clone + Send + Sync {
+    /// store type created by this config
+    type Store: MVStore<Key, Value>;
+    /// create an empty store
+    fn build(&self) -> Self::Store;
+}
@@ -0,0 +1,76 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{reorder::KeySet, types::TxnIndex};
+
+/// split txns into conflict-free partitions by their key sets,
+/// two transactions are in the same partition if they access (read or write) a common key.
+///
+/// indices in each partition are ascending,partitions are ordered by their first transaction.
+pub fn partition<T, K, F>(txns: &[T], key_set: F) -> Vec<Vec<TxnIndex>>
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> KeySet<K>,
+{
+    let mut parent: Vec<TxnIndex> = (0..txns.len()).collect();
+    let mut first_accessor = HashMap::new();
+    for (txn_idx, txn) in txns.iter().enumerate() {
+        let KeySet { reads, writes } = key_set(txn);
+        for key in reads.into_iter().chain(writes) {
+            let other = *first_accessor.entry(key).or_insert(txn_idx);
+            union(&mut parent, txn_idx, other);
+        }
+    }
+    let mut partition_idx = HashMap::new();
+    let mut partitions: Vec<Vec<TxnIndex>> = Vec::new();
+    for txn_idx in 0..txns.len() {
+        let root = find(&mut parent, txn_idx);
+        let idx = *partition_idx.entry(root).or_insert_with(|| {
+            partitions.push(vec![]);
+            partitions.len() - 1
+        });
+        partitions[idx].push(txn_idx);
+    }
+    partitions
+}
+/// pack partitions into at most `num_shards` shards with balanced transaction numbers,
+/// indices in each shard are ascending.
+pub fn shard(mut partitions: Vec<Vec<TxnIndex>>, num_shards: usize) -> Vec<Vec<TxnIndex>> {
+    // largest partition first,into the least loaded shard
+    partitions.sort_by_key(|partition| std::cmp::Reverse(partition.len()));
+    let mut shards: Vec<Vec<TxnIndex>> = vec![vec![]; num_shards.min(partitions.len())];
+    for partition in partitions {
+        if let Some(shard) = shards.iter_mut().min_by_key(|shard| shard.len()) {
+            shard.extend(partition);
+        }
+    }
+    shards.iter_mut().for_each(|shard| shard.sort_unstable());
+    shards
+}
+/// split `concurrency_level` among shards proportionally to their sizes,each shard gets at least one,
+/// number of shards must not exceed `concurrency_level`.
+pub(crate) fn split_concurrency(shards: &[Vec<TxnIndex>], concurrency_level: usize) -> Vec<usize> {
+    let total: usize = shards.iter().map(|shard| shard.len()).sum();
+    let extra = concurrency_level - shards.len();
+    let mut levels: Vec<usize> = shards
+        .iter()
+        .map(|shard| 1 + extra * shard.len() / total.max(1))
+        .collect();
+    // leftovers from rounding down go to the first shards
+    let left = concurrency_level - levels.iter().sum::<usize>();
+    levels.iter_mut().take(left).for_each(|level| *level += 1);
+    levels
+}
+fn find(parent: &mut [TxnIndex], mut idx: TxnIndex) -> TxnIndex {
+    while parent[idx] != idx {
+        parent[idx] = parent[parent[idx]];
+        idx = parent[idx];
+    }
+    idx
+}
+fn union(parent: &mut [TxnIndex], a: TxnIndex, b: TxnIndex) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    if a != b {
+        // keep the smaller index as root
+        parent[a.max(b)] = a.min(b);
+    }
+}
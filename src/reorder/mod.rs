@@ -0,0 +1,126 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// keys declared (or predicted) to be accessed by a transaction
+#[derive(Clone, Debug, Default)]
+pub struct KeySet<K> {
+    /// keys read by transaction
+    pub reads: Vec<K>,
+    /// keys written by transaction
+    pub writes: Vec<K>,
+}
+/// reorder strategy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReorderStrategy {
+    /// spread transactions writing the same hot key apart,so they are less likely to run concurrently
+    Spread,
+    /// cluster transactions writing the same hot key together,so they don't interleave with independent ones
+    Cluster,
+}
+/// reorder txns by their key sets,return reordered txns and their original indices.
+///
+/// transactions are grouped by their hottest conflicting key,i.e. the key written by some transaction in block
+/// and accessed (read or written) by most transactions,so readers of a key are grouped with its writers,
+/// the relative order inside each group is kept.
+pub fn reorder<T, K, F>(txns: Vec<T>, key_set: F, strategy: ReorderStrategy) -> (Vec<T>, Vec<usize>)
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> KeySet<K>,
+{
+    let key_sets: Vec<_> = txns.iter().map(key_set).collect();
+    // distinct keys accessed by each transaction
+    let accesses: Vec<HashSet<&K>> = key_sets
+        .iter()
+        .map(|key_set| key_set.reads.iter().chain(&key_set.writes).collect())
+        .collect();
+    let written: HashSet<&K> = key_sets
+        .iter()
+        .flat_map(|key_set| &key_set.writes)
+        .collect();
+    let mut access_cnt = HashMap::new();
+    for key in accesses.iter().flatten() {
+        *access_cnt.entry(*key).or_insert(0usize) += 1;
+    }
+    // groups in order of first appearance,transactions without conflicting keys form their own groups
+    let mut group_idx = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (txn_idx, key_set) in key_sets.iter().enumerate() {
+        let hot_key = key_set
+            .writes
+            .iter()
+            .chain(&key_set.reads)
+            .filter(|key| written.contains(key))
+            .max_by_key(|key| access_cnt[key])
+            .filter(|key| access_cnt[key] > 1);
+        match hot_key {
+            Some(hot_key) => {
+                let idx = *group_idx.entry(hot_key).or_insert_with(|| {
+                    groups.push(vec![]);
+                    groups.len() - 1
+                });
+                groups[idx].push(txn_idx);
+            }
+            None => groups.push(vec![txn_idx]),
+        }
+    }
+    let order = match strategy {
+        ReorderStrategy::Spread => {
+            // round robin over groups,bigger groups first
+            groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+            let rounds = groups.first().map_or(0, |group| group.len());
+            (0..rounds)
+                .flat_map(|round| groups.iter().filter_map(move |group| group.get(round)))
+                .copied()
+                .collect::<Vec<_>>()
+        }
+        ReorderStrategy::Cluster => groups.into_iter().flatten().collect(),
+    };
+    let mut txns: Vec<_> = txns.into_iter().map(Some).collect();
+    let reordered = order
+        .iter()
+        .map(|&txn_idx| txns[txn_idx].take().expect("txn must be reordered once"))
+        .collect();
+    (reordered, order)
+}
+#[cfg(test)]
+mod tests {
+    use super::{reorder, KeySet, ReorderStrategy};
+
+    fn key_set(key: &usize) -> KeySet<usize> {
+        KeySet {
+            reads: vec![*key],
+            writes: vec![*key],
+        }
+    }
+    #[test]
+    fn spread_and_cluster() {
+        // txn writes the key equal to its value
+        let txns = vec![0, 0, 1, 0, 1, 2];
+        let (spread, order) = reorder(txns.clone(), key_set, ReorderStrategy::Spread);
+        assert_eq!(spread, vec![0, 1, 2, 0, 1, 0]);
+        assert_eq!(order, vec![0, 2, 5, 1, 4, 3]);
+        let (cluster, order) = reorder(txns, key_set, ReorderStrategy::Cluster);
+        assert_eq!(cluster, vec![0, 0, 0, 1, 1, 2]);
+        assert_eq!(order, vec![0, 1, 3, 2, 4, 5]);
+    }
+    #[test]
+    fn readers_grouped_with_writer() {
+        // (read keys,written keys),key 0 is written by txn 0 and read by txns 1 and 3
+        let txns = vec![
+            (vec![], vec![0]),
+            (vec![0], vec![1]),
+            (vec![], vec![2]),
+            (vec![0], vec![3]),
+        ];
+        let key_set = |(reads, writes): &(Vec<usize>, Vec<usize>)| KeySet {
+            reads: reads.clone(),
+            writes: writes.clone(),
+        };
+        let (_, order) = reorder(txns.clone(), key_set, ReorderStrategy::Spread);
+        assert_eq!(order, vec![0, 2, 1, 3]);
+        let (_, order) = reorder(txns, key_set, ReorderStrategy::Cluster);
+        assert_eq!(order, vec![0, 1, 3, 2]);
+    }
+}
@@ -1,5 +1,15 @@
-use crate::types::{AtomicBool, AtomicUsize, Condvar, Incarnation, Mutex, TxnIndex, Version};
-use std::{cmp::min, hint, sync::atomic::Ordering};
+use crate::types::{
+    AtomicBool, AtomicUsize, Condvar, Incarnation, Mutex, Parking, TxnIndex, Version,
+};
+use std::{cmp::min, fmt, hint, sync::atomic::Ordering};
+
+use self::status::{TransactionStatus, TxnStatus};
+pub use self::watchdog::Watchdog;
+
+/// transaction status
+mod status;
+/// watchdog
+mod watchdog;
 /// scheduler
 pub struct Scheduler {
     block_size: usize,
@@ -7,9 +17,17 @@ pub struct Scheduler {
     validation_idx: AtomicUsize,
     num_active_tasks: AtomicUsize,
     decrease_cnt: AtomicUsize,
+    /// number of aborted incarnations,used by execution report
+    num_aborts: AtomicUsize,
+    /// number of workers blocked on dependencies,used by adaptive concurrency
+    num_blocked: AtomicUsize,
+    /// workers parked by adaptive concurrency
+    parking: Parking,
     done_marker: AtomicBool,
+    /// set by watchdog when no progress,used to stop executors
+    halt_marker: AtomicBool,
     txn_dependency: Vec<Mutex<Vec<TxnIndex>>>,
-    txn_status: Vec<Mutex<TransactionStatus>>,
+    txn_status: Vec<TxnStatus>,
 }
 /// public methods used by other components
 impl Scheduler {
@@ -20,13 +38,36 @@ impl Scheduler {
             validation_idx: AtomicUsize::new(0),
             num_active_tasks: AtomicUsize::new(0),
             decrease_cnt: AtomicUsize::new(0),
+            num_aborts: AtomicUsize::new(0),
+            num_blocked: AtomicUsize::new(0),
+            parking: Parking::new(),
             done_marker: AtomicBool::new(false),
+            halt_marker: AtomicBool::new(false),
             txn_dependency: (0..block_size).map(|_| Mutex::new(vec![])).collect(),
-            txn_status: (0..block_size)
-                .map(|_| Mutex::new(TransactionStatus::ReadyToExecute(0, None)))
-                .collect(),
+            txn_status: (0..block_size).map(|_| TxnStatus::new()).collect(),
         }
     }
+    /// reset scheduler for next block,keep allocations for reuse
+    pub fn reset(&mut self, block_size: usize) {
+        self.block_size = block_size;
+        self.execution_idx = AtomicUsize::new(0);
+        self.validation_idx = AtomicUsize::new(0);
+        self.num_active_tasks = AtomicUsize::new(0);
+        self.decrease_cnt = AtomicUsize::new(0);
+        self.num_aborts = AtomicUsize::new(0);
+        self.num_blocked = AtomicUsize::new(0);
+        self.done_marker = AtomicBool::new(false);
+        self.halt_marker = AtomicBool::new(false);
+        self.txn_dependency
+            .iter()
+            .for_each(|dependency| dependency.lock().clear());
+        self.txn_dependency
+            .resize_with(block_size, || Mutex::new(vec![]));
+        self.txn_status
+            .iter_mut()
+            .for_each(|status| *status = TxnStatus::new());
+        self.txn_status.resize_with(block_size, TxnStatus::new);
+    }
     pub fn next_task(&self) -> SchedulerTask {
         loop {
             if self.done() {
@@ -44,13 +85,14 @@ impl Scheduler {
         }
     }
     pub fn abort(&self, txn_idx: TxnIndex, incarnation: Incarnation) -> bool {
-        let mut guard = self.txn_status[txn_idx].lock();
-        if TransactionStatus::Executed(incarnation) == *guard {
-            *guard = TransactionStatus::Aborting(incarnation);
-            true
-        } else {
-            false
+        let aborted = self.txn_status[txn_idx].abort(incarnation);
+        if aborted {
+            self.num_aborts.increment();
         }
+        aborted
+    }
+    pub fn num_aborts(&self) -> usize {
+        self.num_aborts.load()
     }
     pub fn wait_for_dependency(
         &self,
@@ -68,6 +110,35 @@ impl Scheduler {
         }
         Some(condvar)
     }
+    /// block worker until dependency is resolved,see `wait_for_dependency`
+    pub fn wait(&self, condvar: Condvar) {
+        self.num_blocked.increment();
+        // a parked worker may take over
+        self.parking.unpark_all();
+        condvar.wait();
+        self.num_blocked.decrement();
+    }
+    pub fn num_blocked(&self) -> usize {
+        self.num_blocked.load()
+    }
+    /// park worker between tasks until it `admits` the number of blocked workers or scheduler is done,
+    /// return whether admitted.
+    /// parked workers are woken up when a worker blocks,scheduler is done or `unpark` is called
+    pub fn park(&self, admits: impl Fn(usize) -> bool) -> bool {
+        self.parking.park_until(|| {
+            if admits(self.num_blocked.load()) {
+                Some(true)
+            } else if self.done() {
+                Some(false)
+            } else {
+                None
+            }
+        })
+    }
+    /// wake up parked workers to check admission again,see `park`
+    pub fn unpark(&self) {
+        self.parking.unpark_all();
+    }
     pub fn finish_execution<'a>(
         &self,
         txn_idx: TxnIndex,
@@ -107,6 +178,35 @@ impl Scheduler {
         }
         SchedulerTask::NoTask
     }
+    /// stop scheduling and wake up all waiting transactions,used by watchdog
+    pub fn halt(&self) {
+        self.halt_marker.store(true);
+        self.done_marker.store(true);
+        self.txn_status.iter().for_each(|status| status.wake());
+        self.parking.unpark_all();
+    }
+    pub fn halted(&self) -> bool {
+        self.halt_marker.load()
+    }
+    /// all transactions are committed (or scheduler is halted)
+    pub fn done(&self) -> bool {
+        self.done_marker.load()
+    }
+    /// capture current scheduler state,used by watchdog
+    pub fn state(&self) -> SchedulerState {
+        SchedulerState {
+            execution_idx: self.execution_idx.load(),
+            validation_idx: self.validation_idx.load(),
+            num_active_tasks: self.num_active_tasks.load(),
+            decrease_cnt: self.decrease_cnt.load(),
+            txn_status: self.txn_status.iter().map(|s| s.status()).collect(),
+            txn_dependency: self
+                .txn_dependency
+                .iter()
+                .map(|d| d.lock().clone())
+                .collect(),
+        }
+    }
 }
 /// private methods used by scheduler itself
 impl Scheduler {
@@ -118,9 +218,6 @@ impl Scheduler {
         self.validation_idx.fetch_min(target_idx, Ordering::SeqCst);
         self.decrease_cnt.increment();
     }
-    fn done(&self) -> bool {
-        self.done_marker.load()
-    }
     fn check_done(&self) -> bool {
         let observed_cnt = self.decrease_cnt.load();
         let execution_idx = self.execution_idx.load();
@@ -132,6 +229,7 @@ impl Scheduler {
         if observed_cnt == self.decrease_cnt.load() {
             // TODO: `aptos-core` use release order,why?
             self.done_marker.store(true);
+            self.parking.unpark_all();
             true
         } else {
             false
@@ -139,14 +237,10 @@ impl Scheduler {
     }
     fn try_incarnate(&self, txn_idx: TxnIndex) -> Option<(Incarnation, Option<Condvar>)> {
         if txn_idx < self.block_size {
-            let mut guard = self.txn_status[txn_idx].lock();
-            if let TransactionStatus::ReadyToExecute(incarnation, condvar) = &*guard {
-                let result = Some((*incarnation, condvar.clone()));
-                *guard = TransactionStatus::Executing(*incarnation);
-                return result;
-            }
+            self.txn_status[txn_idx].try_incarnate()
+        } else {
+            None
         }
-        None
     }
     fn next_version_to_execute(&self) -> Option<(Version, Option<Condvar>, TaskGuard)> {
         let idx_to_execute = self.execution_idx.load();
@@ -193,64 +287,61 @@ impl Scheduler {
         if txn_idx >= self.block_size {
             return None;
         }
-        let guard = self.txn_status[txn_idx].lock();
-        if let TransactionStatus::Executed(incarnation) = *guard {
-            Some(incarnation)
-        } else {
-            None
-        }
+        self.txn_status[txn_idx].is_executed()
     }
     fn suspend(&self, txn_idx: TxnIndex, condvar: Condvar) {
-        let mut guard = self.txn_status[txn_idx].lock();
-        if let TransactionStatus::Executing(incarnation) = *guard {
-            *guard = TransactionStatus::Suspended(incarnation, condvar);
-        } else {
-            unreachable!()
+        self.txn_status[txn_idx].suspend(condvar.clone());
+        // `halt` may have scanned this transaction before it suspended,wake up itself
+        if self.halted() {
+            condvar.notify_one();
         }
     }
     fn resume(&self, txn_idx: TxnIndex) {
-        let mut guard = self.txn_status[txn_idx].lock();
-        if let TransactionStatus::Suspended(incarnation, condvar) = &*guard {
-            *guard = TransactionStatus::ReadyToExecute(*incarnation, Some(condvar.clone()));
-        } else {
-            unreachable!()
-        }
+        self.txn_status[txn_idx].resume();
     }
     fn set_executed_status(&self, txn_idx: TxnIndex) {
-        let mut guard = self.txn_status[txn_idx].lock();
-        if let TransactionStatus::Executing(incarnation) = *guard {
-            *guard = TransactionStatus::Executed(incarnation);
-        } else {
-            unreachable!()
-        }
+        self.txn_status[txn_idx].set_executed();
     }
     fn set_ready_status(&self, txn_idx: TxnIndex) {
-        let mut guard = self.txn_status[txn_idx].lock();
-        if let TransactionStatus::Aborting(incarnation) = *guard {
-            *guard = TransactionStatus::ReadyToExecute(incarnation + 1, None);
-        } else {
-            unreachable!()
-        }
+        self.txn_status[txn_idx].set_ready();
     }
 }
-/// transaction status,`Suspended` and `Condvar` are used to suspend and resume transaction(due to dependency)
-enum TransactionStatus {
-    ReadyToExecute(Incarnation, Option<Condvar>),
-    Executing(Incarnation),
-    Suspended(Incarnation, Condvar),
-    Executed(Incarnation),
-    Aborting(Incarnation),
+/// scheduler state captured by `Scheduler::state`
+pub struct SchedulerState {
+    execution_idx: usize,
+    validation_idx: usize,
+    num_active_tasks: usize,
+    decrease_cnt: usize,
+    txn_status: Vec<TransactionStatus>,
+    txn_dependency: Vec<Vec<TxnIndex>>,
+}
+impl SchedulerState {
+    /// `num_active_tasks` is excluded,because idle executors keep changing it in `next_task`
+    pub fn same_progress(&self, other: &Self) -> bool {
+        self.execution_idx == other.execution_idx
+            && self.validation_idx == other.validation_idx
+            && self.decrease_cnt == other.decrease_cnt
+            && self.txn_status == other.txn_status
+            && self.txn_dependency == other.txn_dependency
+    }
 }
-impl PartialEq for TransactionStatus {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::ReadyToExecute(l0, _), Self::ReadyToExecute(r0, _)) => l0 == r0,
-            (Self::Executing(l0), Self::Executing(r0)) => l0 == r0,
-            (Self::Suspended(l0, _), Self::Suspended(r0, _)) => l0 == r0,
-            (Self::Executed(l0), Self::Executed(r0)) => l0 == r0,
-            (Self::Aborting(l0), Self::Aborting(r0)) => l0 == r0,
-            _ => false,
+impl fmt::Display for SchedulerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "execution_idx:{},validation_idx:{},num_active_tasks:{},decrease_cnt:{}",
+            self.execution_idx, self.validation_idx, self.num_active_tasks, self.decrease_cnt
+        )?;
+        for (txn_idx, (status, dependency)) in
+            self.txn_status.iter().zip(&self.txn_dependency).enumerate()
+        {
+            writeln!(
+                f,
+                "txn {}:{:?},txn_dependency:{:?}",
+                txn_idx, status, dependency
+            )?;
         }
+        Ok(())
     }
 }
 /// scheduler task guard,used to track the number of active scheduler tasks
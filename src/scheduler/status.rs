@@ -0,0 +1,337 @@
+use std::fmt;
+
+use crate::types::{Condvar, Incarnation};
+
+#[cfg(not(feature = "mutex_status"))]
+use crate::types::{AtomicUsize, Mutex};
+#[cfg(not(feature = "mutex_status"))]
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "mutex_status")]
+use crate::types::Mutex;
+
+/// transaction status,`Suspended` and `Condvar` are used to suspend and resume transaction(due to dependency)
+#[derive(Clone)]
+pub enum TransactionStatus {
+    ReadyToExecute(Incarnation, Option<Condvar>),
+    Executing(Incarnation),
+    Suspended(Incarnation, Condvar),
+    Executed(Incarnation),
+    Aborting(Incarnation),
+}
+impl PartialEq for TransactionStatus {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::ReadyToExecute(l0, _), Self::ReadyToExecute(r0, _)) => l0 == r0,
+            (Self::Executing(l0), Self::Executing(r0)) => l0 == r0,
+            (Self::Suspended(l0, _), Self::Suspended(r0, _)) => l0 == r0,
+            (Self::Executed(l0), Self::Executed(r0)) => l0 == r0,
+            (Self::Aborting(l0), Self::Aborting(r0)) => l0 == r0,
+            _ => false,
+        }
+    }
+}
+impl fmt::Debug for TransactionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReadyToExecute(incarnation, None) => write!(f, "ReadyToExecute({})", incarnation),
+            Self::ReadyToExecute(incarnation, Some(_)) => {
+                write!(f, "ReadyToExecute({},resumed)", incarnation)
+            }
+            Self::Executing(incarnation) => write!(f, "Executing({})", incarnation),
+            Self::Suspended(incarnation, _) => write!(f, "Suspended({})", incarnation),
+            Self::Executed(incarnation) => write!(f, "Executed({})", incarnation),
+            Self::Aborting(incarnation) => write!(f, "Aborting({})", incarnation),
+        }
+    }
+}
+/// methods shared by both transaction status implementations
+impl TxnStatus {
+    /// wake up the suspended (or resumed but not yet incarnated) transaction,if any
+    pub fn wake(&self) {
+        match self.status() {
+            TransactionStatus::Suspended(_, condvar)
+            | TransactionStatus::ReadyToExecute(_, Some(condvar)) => condvar.notify_one(),
+            _ => {}
+        }
+    }
+}
+#[cfg(not(feature = "mutex_status"))]
+const READY_TO_EXECUTE: usize = 0;
+#[cfg(not(feature = "mutex_status"))]
+const EXECUTING: usize = 1;
+#[cfg(not(feature = "mutex_status"))]
+const SUSPENDED: usize = 2;
+#[cfg(not(feature = "mutex_status"))]
+const EXECUTED: usize = 3;
+#[cfg(not(feature = "mutex_status"))]
+const ABORTING: usize = 4;
+#[cfg(not(feature = "mutex_status"))]
+const KIND_BITS: usize = 3;
+/// set while suspension handle is stored,i.e. from suspension until next incarnation
+#[cfg(not(feature = "mutex_status"))]
+const WAITER: usize = 1 << KIND_BITS;
+#[cfg(not(feature = "mutex_status"))]
+const INCARNATION_SHIFT: usize = KIND_BITS + 1;
+/// transaction status packed into an atomic word (`incarnation << INCARNATION_SHIFT | waiter | kind`),
+/// transitions are CAS operations.
+///
+/// suspension handle is stored separately,it's only locked when waiter bit is set,
+/// i.e. when transaction suspends on dependency.
+#[cfg(not(feature = "mutex_status"))]
+pub struct TxnStatus {
+    word: AtomicUsize,
+    condvar: Mutex<Option<Condvar>>,
+}
+#[cfg(not(feature = "mutex_status"))]
+impl TxnStatus {
+    pub fn new() -> Self {
+        Self {
+            word: AtomicUsize::new(Self::pack(READY_TO_EXECUTE, 0, false)),
+            condvar: Mutex::new(None),
+        }
+    }
+    /// `Executed(incarnation)` -> `Aborting(incarnation)`
+    pub fn abort(&self, incarnation: Incarnation) -> bool {
+        self.transit(
+            Self::pack(EXECUTED, incarnation, false),
+            Self::pack(ABORTING, incarnation, false),
+        )
+    }
+    /// `ReadyToExecute(incarnation, condvar)` -> `Executing(incarnation)`
+    pub fn try_incarnate(&self) -> Option<(Incarnation, Option<Condvar>)> {
+        let word = self.word.load();
+        let (kind, incarnation, waiter) = Self::unpack(word);
+        if kind == READY_TO_EXECUTE && self.transit(word, Self::pack(EXECUTING, incarnation, false))
+        {
+            // condvar is only present if transaction was resumed
+            let condvar = if waiter {
+                self.condvar.lock().take()
+            } else {
+                None
+            };
+            Some((incarnation, condvar))
+        } else {
+            None
+        }
+    }
+    pub fn is_executed(&self) -> Option<Incarnation> {
+        let (kind, incarnation, _) = Self::unpack(self.word.load());
+        if kind == EXECUTED {
+            Some(incarnation)
+        } else {
+            None
+        }
+    }
+    /// `Executing(incarnation)` -> `Suspended(incarnation, condvar)`
+    pub fn suspend(&self, condvar: Condvar) {
+        // handle is stored before waiter bit is published
+        *self.condvar.lock() = Some(condvar);
+        self.transit_from(EXECUTING, SUSPENDED, 0, true);
+    }
+    /// `Suspended(incarnation, condvar)` -> `ReadyToExecute(incarnation, Some(condvar))`
+    pub fn resume(&self) {
+        self.transit_from(SUSPENDED, READY_TO_EXECUTE, 0, true);
+    }
+    /// `Executing(incarnation)` -> `Executed(incarnation)`
+    pub fn set_executed(&self) {
+        self.transit_from(EXECUTING, EXECUTED, 0, false);
+    }
+    /// `Aborting(incarnation)` -> `ReadyToExecute(incarnation + 1, None)`
+    pub fn set_ready(&self) {
+        self.transit_from(ABORTING, READY_TO_EXECUTE, 1, false);
+    }
+    pub fn status(&self) -> TransactionStatus {
+        let (kind, incarnation, waiter) = Self::unpack(self.word.load());
+        let condvar = if waiter {
+            self.condvar.lock().clone()
+        } else {
+            None
+        };
+        match (kind, condvar) {
+            (READY_TO_EXECUTE, condvar) => TransactionStatus::ReadyToExecute(incarnation, condvar),
+            (EXECUTING, _) => TransactionStatus::Executing(incarnation),
+            // handle may be already taken by a racing resume and incarnation
+            (SUSPENDED, condvar) => {
+                TransactionStatus::Suspended(incarnation, condvar.unwrap_or_default())
+            }
+            (EXECUTED, _) => TransactionStatus::Executed(incarnation),
+            (ABORTING, _) => TransactionStatus::Aborting(incarnation),
+            _ => unreachable!(),
+        }
+    }
+    fn pack(kind: usize, incarnation: Incarnation, waiter: bool) -> usize {
+        incarnation << INCARNATION_SHIFT | if waiter { WAITER } else { 0 } | kind
+    }
+    fn unpack(word: usize) -> (usize, Incarnation, bool) {
+        (
+            word & (WAITER - 1),
+            word >> INCARNATION_SHIFT,
+            word & WAITER != 0,
+        )
+    }
+    fn transit(&self, current: usize, new: usize) -> bool {
+        self.word
+            .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+    /// transit from `kind` to `new_kind`,`increment` added to incarnation
+    fn transit_from(&self, kind: usize, new_kind: usize, increment: Incarnation, waiter: bool) {
+        let word = self.word.load();
+        let (current_kind, incarnation, _) = Self::unpack(word);
+        if current_kind != kind
+            || !self.transit(word, Self::pack(new_kind, incarnation + increment, waiter))
+        {
+            unreachable!()
+        }
+    }
+}
+/// transaction status protected by mutex,used to benchmark against the atomic one
+#[cfg(feature = "mutex_status")]
+pub struct TxnStatus(Mutex<TransactionStatus>);
+#[cfg(feature = "mutex_status")]
+impl TxnStatus {
+    pub fn new() -> Self {
+        Self(Mutex::new(TransactionStatus::ReadyToExecute(0, None)))
+    }
+    pub fn abort(&self, incarnation: Incarnation) -> bool {
+        let mut guard = self.0.lock();
+        if TransactionStatus::Executed(incarnation) == *guard {
+            *guard = TransactionStatus::Aborting(incarnation);
+            true
+        } else {
+            false
+        }
+    }
+    pub fn try_incarnate(&self) -> Option<(Incarnation, Option<Condvar>)> {
+        let mut guard = self.0.lock();
+        if let TransactionStatus::ReadyToExecute(incarnation, condvar) = &*guard {
+            let result = Some((*incarnation, condvar.clone()));
+            *guard = TransactionStatus::Executing(*incarnation);
+            return result;
+        }
+        None
+    }
+    pub fn is_executed(&self) -> Option<Incarnation> {
+        let guard = self.0.lock();
+        if let TransactionStatus::Executed(incarnation) = *guard {
+            Some(incarnation)
+        } else {
+            None
+        }
+    }
+    pub fn suspend(&self, condvar: Condvar) {
+        let mut guard = self.0.lock();
+        if let TransactionStatus::Executing(incarnation) = *guard {
+            *guard = TransactionStatus::Suspended(incarnation, condvar);
+        } else {
+            unreachable!()
+        }
+    }
+    pub fn resume(&self) {
+        let mut guard = self.0.lock();
+        if let TransactionStatus::Suspended(incarnation, condvar) = &*guard {
+            *guard = TransactionStatus::ReadyToExecute(*incarnation, Some(condvar.clone()));
+        } else {
+            unreachable!()
+        }
+    }
+    pub fn set_executed(&self) {
+        let mut guard = self.0.lock();
+        if let TransactionStatus::Executing(incarnation) = *guard {
+            *guard = TransactionStatus::Executed(incarnation);
+        } else {
+            unreachable!()
+        }
+    }
+    pub fn set_ready(&self) {
+        let mut guard = self.0.lock();
+        if let TransactionStatus::Aborting(incarnation) = *guard {
+            *guard = TransactionStatus::ReadyToExecute(incarnation + 1, None);
+        } else {
+            unreachable!()
+        }
+    }
+    pub fn status(&self) -> TransactionStatus {
+        self.0.lock().clone()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{TransactionStatus, TxnStatus};
+    use crate::types::Condvar;
+
+    #[test]
+    fn transitions() {
+        let status = TxnStatus::new();
+        assert_eq!(status.status(), TransactionStatus::ReadyToExecute(0, None));
+        assert!(matches!(status.try_incarnate(), Some((0, None))));
+        assert!(status.try_incarnate().is_none());
+        assert_eq!(status.status(), TransactionStatus::Executing(0));
+        assert!(!status.abort(0));
+        status.set_executed();
+        assert_eq!(status.is_executed(), Some(0));
+        assert!(status.abort(0));
+        assert_eq!(status.status(), TransactionStatus::Aborting(0));
+        assert_eq!(status.is_executed(), None);
+        status.set_ready();
+        assert_eq!(status.status(), TransactionStatus::ReadyToExecute(1, None));
+        assert!(matches!(status.try_incarnate(), Some((1, None))));
+        status.set_executed();
+        // stale incarnation loses
+        assert!(!status.abort(0));
+        assert_eq!(status.is_executed(), Some(1));
+    }
+    #[test]
+    fn suspension() {
+        let status = TxnStatus::new();
+        status.try_incarnate();
+        status.suspend(Condvar::new());
+        assert!(matches!(
+            status.status(),
+            TransactionStatus::Suspended(0, _)
+        ));
+        assert!(status.try_incarnate().is_none());
+        status.resume();
+        assert!(matches!(
+            status.status(),
+            TransactionStatus::ReadyToExecute(0, Some(_))
+        ));
+        // resumed incarnation takes the handle,next status doesn't carry it
+        assert!(matches!(status.try_incarnate(), Some((0, Some(_)))));
+        status.set_executed();
+        status.abort(0);
+        status.set_ready();
+        assert_eq!(status.status(), TransactionStatus::ReadyToExecute(1, None));
+        assert!(matches!(status.try_incarnate(), Some((1, None))));
+    }
+    #[test]
+    fn races() {
+        let status = TxnStatus::new();
+        let (incarnated, aborted) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        crossbeam::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|_| {
+                    if status.try_incarnate().is_some() {
+                        incarnated.fetch_add(1, Ordering::SeqCst);
+                    }
+                });
+            }
+        })
+        .unwrap();
+        status.set_executed();
+        crossbeam::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|_| {
+                    if status.abort(0) {
+                        aborted.fetch_add(1, Ordering::SeqCst);
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!((incarnated.into_inner(), aborted.into_inner()), (1, 1));
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use crossbeam::channel::{Receiver, RecvTimeoutError};
+
+use super::{Scheduler, SchedulerState};
+
+/// watchdog,used to detect transactions which stop changing state (e.g. hangs in `next_task`)
+pub struct Watchdog<'a> {
+    scheduler: &'a Scheduler,
+    interval: Duration,
+}
+impl<'a> Watchdog<'a> {
+    pub fn new(scheduler: &'a Scheduler, interval: Duration) -> Self {
+        Self {
+            scheduler,
+            interval,
+        }
+    }
+    /// watch scheduler until `stop` is signaled or disconnected,or scheduler is done
+    /// (e.g. shard of partitioned block finished before others),
+    /// halt scheduler and return its state if no progress within interval.
+    pub fn watch(&self, stop: Receiver<()>) -> Result<(), SchedulerState> {
+        let mut last_state = self.scheduler.state();
+        loop {
+            match stop.recv_timeout(self.interval) {
+                Ok(_) | Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.scheduler.done() {
+                        return Ok(());
+                    }
+                    let state = self.scheduler.state();
+                    if state.same_progress(&last_state) {
+                        self.scheduler.halt();
+                        return Err(state);
+                    }
+                    last_state = state;
+                }
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crate::scheduler::{Scheduler, SchedulerTask};
+
+    use super::Watchdog;
+
+    /// run all tasks of scheduler on current thread,sleeping `delay` in each execution
+    fn drive(scheduler: &Scheduler, delay: Duration) {
+        loop {
+            let mut task = scheduler.next_task();
+            loop {
+                task = match task {
+                    SchedulerTask::Execution((txn_idx, incarnation), _, guard) => {
+                        thread::sleep(delay);
+                        scheduler.finish_execution(txn_idx, incarnation, false, guard)
+                    }
+                    SchedulerTask::Validation((txn_idx, _), guard) => {
+                        scheduler.finish_validation(txn_idx, false, guard)
+                    }
+                    SchedulerTask::NoTask => break,
+                    SchedulerTask::Done => return,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn halt_without_progress() {
+        let scheduler = Scheduler::new(2);
+        // take an execution task and never finish it
+        let task = scheduler.next_task();
+        assert!(matches!(task, SchedulerTask::Execution((0, 0), None, _)));
+        let (_stop_tx, stop_rx) = crossbeam::channel::bounded(1);
+        let state = Watchdog::new(&scheduler, Duration::from_millis(50))
+            .watch(stop_rx)
+            .expect_err("watchdog should detect no progress");
+        assert!(scheduler.halted());
+        assert!(matches!(scheduler.next_task(), SchedulerTask::Done));
+        let dump = state.to_string();
+        assert!(dump.contains("txn 0:Executing(0)"));
+        assert!(dump.contains("txn 1:ReadyToExecute(0)"));
+    }
+    #[test]
+    fn finished_shard_with_running_shard() {
+        let interval = Duration::from_millis(50);
+        let tiny = Scheduler::new(1);
+        let slow = Scheduler::new(8);
+        // watchdogs of partitioned block share one stop channel,dropped when all shards finish
+        let (stop_tx, stop_rx) = crossbeam::channel::bounded::<()>(1);
+        crossbeam::scope(|s| {
+            let watchdogs: Vec<_> = [&tiny, &slow]
+                .into_iter()
+                .map(|scheduler| {
+                    let stop_rx = stop_rx.clone();
+                    s.spawn(move |_| Watchdog::new(scheduler, interval).watch(stop_rx))
+                })
+                .collect();
+            drive(&tiny, Duration::ZERO);
+            // slow shard keeps making progress for several intervals after tiny one is done
+            drive(&slow, interval / 3);
+            drop(stop_tx);
+            for watchdog in watchdogs {
+                assert!(watchdog.join().unwrap().is_ok());
+            }
+        })
+        .unwrap();
+        assert!(!tiny.halted() && !slow.halted());
+    }
+}
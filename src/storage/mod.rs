@@ -0,0 +1,420 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    hash::Hash,
+    io::{self, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    core::{ValueBytes, WireBytes},
+    types::codec::{
+        read_bytes, read_option, read_u64, read_u8, write_bytes, write_option, write_u64, write_u8,
+    },
+};
+use thiserror::Error;
+
+/// base state read by vm when key is not written by any lower transaction of block
+///
+/// `Sync` needed by rayon
+pub trait BaseView<K, V>: Sync {
+    /// latest committed value of key,`None` if key doesn't exist
+    fn read(&self, key: &K) -> Option<V>;
+}
+/// versioned storage,which is updated by committing blocks
+pub trait Storage<K, V>: BaseView<K, V> {
+    /// number of committed blocks
+    fn version(&self) -> u64;
+    /// atomically apply snapshot of block as a new version,`None` value indicates deletion,
+    /// return the new version.
+    fn commit(&mut self, snapshot: &[(K, Option<V>)]) -> io::Result<u64>;
+}
+/// in-memory storage,reference base state of tests
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InMemoryStorage<K: Eq + Hash, V> {
+    state: HashMap<K, V>,
+    version: u64,
+}
+impl<K: Eq + Hash, V> Default for InMemoryStorage<K, V> {
+    fn default() -> Self {
+        Self {
+            state: HashMap::new(),
+            version: 0,
+        }
+    }
+}
+impl<K: Eq + Hash, V> FromIterator<(K, V)> for InMemoryStorage<K, V> {
+    /// genesis state of version 0
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            state: iter.into_iter().collect(),
+            version: 0,
+        }
+    }
+}
+impl<K: Eq + Hash, V> InMemoryStorage<K, V> {
+    /// latest state
+    pub fn state(&self) -> &HashMap<K, V> {
+        &self.state
+    }
+}
+impl<K, V> BaseView<K, V> for InMemoryStorage<K, V>
+where
+    K: Eq + Hash + Sync,
+    V: Clone + Sync,
+{
+    fn read(&self, key: &K) -> Option<V> {
+        self.state.get(key).cloned()
+    }
+}
+impl<K, V> Storage<K, V> for InMemoryStorage<K, V>
+where
+    K: Eq + Hash + Clone + Sync,
+    V: Clone + Sync,
+{
+    fn version(&self) -> u64 {
+        self.version
+    }
+    fn commit(&mut self, snapshot: &[(K, Option<V>)]) -> io::Result<u64> {
+        for (key, value) in snapshot {
+            match value {
+                Some(value) => self.state.insert(key.clone(), value.clone()),
+                None => self.state.remove(key),
+            };
+        }
+        self.version += 1;
+        Ok(self.version)
+    }
+}
+/// record kinds in log
+const BLOCK: u8 = 0;
+const CHECKPOINT: u8 = 1;
+/// log file name in storage directory
+const LOG: &str = "blocks.log";
+/// log-structured file storage,each committed block is appended to log as a checksummed record,
+/// latest values are indexed in memory.
+///
+/// a block is committed once its record is synced,on open,log is replayed and a torn (or corrupted) last record is truncated,
+/// so crash recovery restores the last fully committed block,a bad record followed by others fails open with `InvalidData`.
+pub struct FileStorage<K, V> {
+    dir: PathBuf,
+    log: File,
+    /// length of the valid prefix of log
+    len: u64,
+    state: HashMap<K, Vec<u8>>,
+    version: u64,
+    phantom: PhantomData<fn() -> V>,
+}
+impl<K, V> FileStorage<K, V>
+where
+    K: WireBytes + Eq + Hash + Clone,
+    V: ValueBytes,
+{
+    /// open (or create) storage in given directory,recover from its log
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut log = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOG))?;
+        // make creation of log durable
+        sync_dir(&dir)?;
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+        let mut storage = Self {
+            dir,
+            log,
+            len: 0,
+            state: HashMap::new(),
+            version: 0,
+            phantom: PhantomData,
+        };
+        storage.len = storage.replay(&bytes)?;
+        // drop torn tail left by crash
+        storage.truncate()?;
+        Ok(storage)
+    }
+    /// rewrite log as a single checkpoint of current state,which is atomically renamed over old log
+    pub fn compact(&mut self) -> io::Result<()> {
+        let tmp = self.dir.join(format!("{}.tmp", LOG));
+        let mut file = File::create(&tmp)?;
+        let entries: Vec<_> = self
+            .state
+            .iter()
+            .map(|(key, value)| (key.to_bytes(), Some(value.as_slice())))
+            .collect();
+        let record = Self::record(CHECKPOINT, self.version, &entries)?;
+        file.write_all(&record)?;
+        file.sync_all()?;
+        fs::rename(&tmp, self.dir.join(LOG))?;
+        // make rename durable
+        sync_dir(&self.dir)?;
+        self.log = OpenOptions::new().write(true).open(self.dir.join(LOG))?;
+        self.len = record.len() as u64;
+        self.truncate()
+    }
+    /// truncate log to its valid prefix,and append after it
+    fn truncate(&mut self) -> io::Result<()> {
+        self.log.set_len(self.len)?;
+        self.log.seek(SeekFrom::Start(self.len))?;
+        Ok(())
+    }
+    /// replay records of log,return length of the valid prefix
+    fn replay(&mut self, bytes: &[u8]) -> io::Result<u64> {
+        let mut offset = 0;
+        while let Some(len) = self.replay_record(&bytes[offset..]).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("corrupted record at offset {} of log: {}", offset, e),
+            )
+        })? {
+            offset += len;
+        }
+        Ok(offset as u64)
+    }
+    /// replay the first record of bytes,return its length,`None` at the end of log or if record is torn,
+    /// i.e. it's the last record and it's incomplete or fails checksum.
+    ///
+    /// a record followed by others was fully written,so it's an error if it's bad,
+    /// with a bad length,it's followed by others if any valid record can be found after it.
+    fn replay_record(&mut self, bytes: &[u8]) -> io::Result<Option<usize>> {
+        let (mut payload, len) = match frame(bytes) {
+            Ok(record) => record,
+            Err(BadRecord::Incomplete) => return Ok(None),
+            Err(BadRecord::Length) if !(1..bytes.len()).any(|i| frame(&bytes[i..]).is_ok()) => {
+                return Ok(None)
+            }
+            Err(BadRecord::Checksum { last: true }) => return Ok(None),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+        };
+        let kind = read_u8(&mut payload)?;
+        let version = read_u64(&mut payload)?;
+        let entries = (0..read_u64(&mut payload)?)
+            .map(|_| Ok((read_bytes(&mut payload)?, read_option(&mut payload)?)))
+            .collect::<io::Result<Vec<_>>>()?;
+        match kind {
+            BLOCK if version == self.version + 1 => {}
+            CHECKPOINT => self.state.clear(),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "record of kind {} and version {} after version {}",
+                        kind, version, self.version
+                    ),
+                ))
+            }
+        }
+        for (key, value) in entries {
+            let key = K::from_bytes(&key);
+            match value {
+                Some(value) => self.state.insert(key, value),
+                None => self.state.remove(&key),
+            };
+        }
+        self.version = version;
+        Ok(Some(len))
+    }
+    /// encode record,which is `length of payload | checksum of length | payload | checksum of payload`
+    #[allow(clippy::type_complexity)]
+    fn record(kind: u8, version: u64, entries: &[(Vec<u8>, Option<&[u8]>)]) -> io::Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        write_u8(&mut payload, kind)?;
+        write_u64(&mut payload, version)?;
+        write_u64(&mut payload, entries.len() as u64)?;
+        for (key, value) in entries {
+            write_bytes(&mut payload, key)?;
+            write_option(&mut payload, *value)?;
+        }
+        let mut record = Vec::new();
+        write_u64(&mut record, payload.len() as u64)?;
+        write_u64(&mut record, fnv1a(&(payload.len() as u64).to_le_bytes()))?;
+        record.extend_from_slice(&payload);
+        write_u64(&mut record, fnv1a(&payload))?;
+        Ok(record)
+    }
+}
+impl<K, V> BaseView<K, V> for FileStorage<K, V>
+where
+    K: WireBytes + Eq + Hash + Clone + Sync,
+    V: ValueBytes,
+{
+    fn read(&self, key: &K) -> Option<V> {
+        self.state.get(key).map(|value| V::deserialize(value))
+    }
+}
+impl<K, V> Storage<K, V> for FileStorage<K, V>
+where
+    K: WireBytes + Eq + Hash + Clone + Sync,
+    V: ValueBytes,
+{
+    fn version(&self) -> u64 {
+        self.version
+    }
+    fn commit(&mut self, snapshot: &[(K, Option<V>)]) -> io::Result<u64> {
+        let values: Vec<_> = snapshot
+            .iter()
+            .map(|(key, value)| (key, value.as_ref().map(|v| v.serialize())))
+            .collect();
+        let entries: Vec<_> = values
+            .iter()
+            .map(|(key, value)| (key.to_bytes(), value.as_deref()))
+            .collect();
+        let version = self.version + 1;
+        let record = Self::record(BLOCK, version, &entries)?;
+        // block is committed once its record is synced
+        if let Err(e) = self
+            .log
+            .write_all(&record)
+            .and_then(|_| self.log.sync_data())
+        {
+            // drop partially written record,so later commits are not appended after a torn one
+            let _ = self.truncate();
+            return Err(e);
+        }
+        self.len += record.len() as u64;
+        for (key, value) in values {
+            match value {
+                Some(value) => self.state.insert(key.clone(), value),
+                None => self.state.remove(key),
+            };
+        }
+        self.version = version;
+        Ok(version)
+    }
+}
+/// why bytes don't start with a valid record
+#[derive(Debug, Error)]
+enum BadRecord {
+    /// record is cut off,or there is no record
+    #[error("incomplete record")]
+    Incomplete,
+    /// length of record fails its checksum,so the record can't be skipped
+    #[error("length checksum mismatch")]
+    Length,
+    /// payload of record fails its checksum
+    #[error("checksum mismatch")]
+    Checksum {
+        /// nothing follows the record
+        last: bool,
+    },
+}
+/// check framing of the first record of bytes,return its payload and length of the whole record
+fn frame(bytes: &[u8]) -> Result<(&[u8], usize), BadRecord> {
+    const HEADER: usize = 16;
+    if bytes.len() < HEADER {
+        return Err(BadRecord::Incomplete);
+    }
+    let (mut header, rest) = bytes.split_at(HEADER);
+    let len = read_u64(&mut header).map_err(|_| BadRecord::Incomplete)?;
+    let checksum = read_u64(&mut header).map_err(|_| BadRecord::Incomplete)?;
+    if checksum != fnv1a(&len.to_le_bytes()) {
+        return Err(BadRecord::Length);
+    }
+    let len = len as usize;
+    if rest.len() < len.saturating_add(8) {
+        return Err(BadRecord::Incomplete);
+    }
+    let (payload, mut rest) = rest.split_at(len);
+    let checksum = read_u64(&mut rest).map_err(|_| BadRecord::Incomplete)?;
+    if checksum != fnv1a(payload) {
+        return Err(BadRecord::Checksum {
+            last: rest.is_empty(),
+        });
+    }
+    Ok((payload, HEADER + len + 8))
+}
+/// sync directory,so that files created or renamed in it survive crash
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+/// 64-bit FNV-1a,used as checksum of records (stable across toolchains,unlike std hasher)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+#[cfg(test)]
+mod tests {
+    use std::{fs::OpenOptions, io::Write};
+
+    use super::{FileStorage, Storage, LOG};
+    use crate::storage::BaseView;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("block_stm_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+    #[test]
+    fn commit_and_recover() {
+        let dir = temp_dir("storage");
+        let mut storage = FileStorage::<usize, usize>::open(&dir).unwrap();
+        assert_eq!(storage.commit(&[(0, Some(10)), (1, Some(20))]).unwrap(), 1);
+        assert_eq!(storage.commit(&[(0, None), (2, Some(30))]).unwrap(), 2);
+        drop(storage);
+        // torn record of a crashed commit
+        OpenOptions::new()
+            .append(true)
+            .open(dir.join(LOG))
+            .unwrap()
+            .write_all(&[42; 13])
+            .unwrap();
+        let mut storage = FileStorage::<usize, usize>::open(&dir).unwrap();
+        assert_eq!(storage.version(), 2);
+        assert_eq!(
+            (storage.read(&0), storage.read(&1), storage.read(&2)),
+            (None, Some(20), Some(30))
+        );
+        // commits after recovery are appended to the valid prefix
+        storage.commit(&[(1, Some(21))]).unwrap();
+        storage.compact().unwrap();
+        storage.commit(&[(3, Some(40))]).unwrap();
+        drop(storage);
+        let storage = FileStorage::<usize, usize>::open(&dir).unwrap();
+        assert_eq!(storage.version(), 4);
+        assert_eq!(
+            (storage.read(&1), storage.read(&2), storage.read(&3)),
+            (Some(21), Some(30), Some(40))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn corrupted_record() {
+        let dir = temp_dir("storage_corrupted");
+        let mut storage = FileStorage::<usize, usize>::open(&dir).unwrap();
+        storage.commit(&[(0, Some(10))]).unwrap();
+        let len = std::fs::metadata(dir.join(LOG)).unwrap().len();
+        storage.commit(&[(0, Some(11))]).unwrap();
+        drop(storage);
+        let flip = |offset: u64| {
+            let mut bytes = std::fs::read(dir.join(LOG)).unwrap();
+            bytes[offset as usize] ^= 0x80;
+            std::fs::write(dir.join(LOG), bytes).unwrap();
+        };
+        // bad record in the middle of log isn't a torn write,whether its payload or its length is bad
+        for offset in [20, 0, 7] {
+            flip(offset);
+            let err = FileStorage::<usize, usize>::open(&dir).err().unwrap();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            flip(offset);
+        }
+        // bad last record is truncated
+        flip(len + 20);
+        let mut storage = FileStorage::<usize, usize>::open(&dir).unwrap();
+        assert_eq!((storage.version(), storage.read(&0)), (1, Some(10)));
+        assert_eq!(std::fs::metadata(dir.join(LOG)).unwrap().len(), len);
+        // so is a last record with bad length
+        storage.commit(&[(0, Some(11))]).unwrap();
+        drop(storage);
+        flip(len + 7);
+        let storage = FileStorage::<usize, usize>::open(&dir).unwrap();
+        assert_eq!((storage.version(), storage.read(&0)), (1, Some(10)));
+        assert_eq!(std::fs::metadata(dir.join(LOG)).unwrap().len(), len);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
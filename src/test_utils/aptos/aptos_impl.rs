@@ -26,6 +26,7 @@ pub fn aptos_parallel_execute(
                 total_time: total.elapsed(),
                 execute_time: Some(execute),
                 collect_time: Some(collect),
+                num_aborts: None,
             },
         )
     } else {
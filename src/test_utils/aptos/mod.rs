@@ -104,6 +104,7 @@ pub fn sequential_execute(
                 total_time,
                 execute_time: None,
                 collect_time: None,
+                num_aborts: None,
             },
         )
     } else {
@@ -115,9 +116,7 @@ mod tests {
     use std::collections::HashMap;
 
     use aptos_e2e_tests::data_store::FakeDataStore;
-    use aptos_types::{
-        state_store::state_key::StateKey, transaction::TransactionOutput, write_set::WriteOp,
-    };
+    use aptos_types::{state_store::state_key::StateKey, transaction::TransactionOutput};
     use either::Either;
 
     use super::{
@@ -127,7 +126,7 @@ mod tests {
 
     fn apply(
         mut state: FakeDataStore,
-        output: Either<Vec<TransactionOutput>, Vec<(StateKey, Option<WriteOp>)>>,
+        output: Either<Vec<TransactionOutput>, Vec<(StateKey, Option<Vec<u8>>)>>,
     ) -> HashMap<StateKey, Vec<u8>> {
         match output {
             Either::Left(outputs) => {
@@ -138,13 +137,12 @@ mod tests {
             Either::Right(output) => {
                 for (k, v) in output {
                     match v {
-                        Some(WriteOp::Creation(blob)) | Some(WriteOp::Modification(blob)) => {
+                        Some(blob) => {
                             state.set(k, blob);
                         }
                         None => {
                             state.remove(&k);
                         }
-                        _ => unreachable!(),
                     }
                 }
             }
@@ -17,40 +17,37 @@ use aptos_vm::{
 };
 
 use crate::{
-    core::{Transaction, TransactionOutput, ValueBytes, VM},
+    core::{self, Event, Transaction, TransactionOutput, ValueBytes, VM},
     mvmemory::{MVMemoryView, ReadResult},
     test_utils::BenchmarkInfo,
     ParallelExecutor,
 };
 
-/// serialize/deserialize WriteOp
-impl ValueBytes for WriteOp {
-    fn serialize(&self) -> Option<Vec<u8>> {
-        match self {
-            WriteOp::Creation(v) | WriteOp::Modification(v) => Some(v.clone()),
-            WriteOp::Deletion => None,
-        }
+/// serialize/deserialize state value blob
+impl ValueBytes for Vec<u8> {
+    fn serialize(&self) -> Vec<u8> {
+        self.clone()
     }
 
     fn deserialize(bytes: &[u8]) -> Self {
-        Self::Modification(bytes.to_vec())
+        bytes.to_vec()
     }
 }
 /// smart contract transaction
 impl Transaction for PreprocessedTransaction {
     type Key = StateKey;
 
-    type Value = WriteOp;
+    type Value = Vec<u8>;
 }
 /// executor view
 pub struct ExecutorView<'a, S: StateView> {
     base_view: &'a S,
-    hashmap_view: &'a MVMemoryView<'a, StateKey, WriteOp>,
+    hashmap_view: &'a MVMemoryView<'a, StateKey, Vec<u8>>,
 }
 impl<'a, S: StateView> ExecutorView<'a, S> {
     pub fn new_view(
         base_view: &'a S,
-        hashmap_view: &'a MVMemoryView<StateKey, WriteOp>,
+        hashmap_view: &'a MVMemoryView<StateKey, Vec<u8>>,
     ) -> StorageAdapterOwned<ExecutorView<'a, S>> {
         Self {
             base_view,
@@ -63,10 +60,8 @@ impl<'a, S: StateView> StateView for ExecutorView<'a, S> {
     // read from hashmap or from storage
     fn get_state_value(&self, state_key: &StateKey) -> anyhow::Result<Option<Vec<u8>>> {
         match self.hashmap_view.read(state_key) {
-            ReadResult::Value(v) => Ok(match v.as_ref() {
-                WriteOp::Creation(w) | WriteOp::Modification(w) => Some(w.clone()),
-                WriteOp::Deletion => None,
-            }),
+            ReadResult::Value(v) => Ok(Some(v.as_ref().clone())),
+            ReadResult::Deleted => Ok(None),
             ReadResult::NotFound => self.base_view.get_state_value(state_key),
         }
     }
@@ -87,11 +82,32 @@ impl TransactionOutput for aptos_types::transaction::TransactionOutput {
         &self,
     ) -> Vec<(
         <Self::T as Transaction>::Key,
-        <Self::T as Transaction>::Value,
+        core::WriteOp<<Self::T as Transaction>::Value>,
     )> {
         self.write_set()
             .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
+            .map(|(k, op)| {
+                let op = match op {
+                    WriteOp::Creation(v) => core::WriteOp::Create(v.clone()),
+                    WriteOp::Modification(v) => core::WriteOp::Modify(v.clone()),
+                    WriteOp::Deletion => core::WriteOp::Delete,
+                };
+                (k.clone(), op)
+            })
+            .collect()
+    }
+
+    fn get_gas_used(&self) -> u64 {
+        self.gas_used()
+    }
+
+    fn get_events(&self) -> Vec<Event> {
+        self.events()
+            .iter()
+            .map(|event| Event {
+                type_tag: event.type_tag().to_string(),
+                data: event.event_data().to_vec(),
+            })
             .collect()
     }
 }
@@ -152,18 +168,23 @@ pub fn my_parallel_execute(
     txns: &Vec<PreprocessedTransaction>,
     state: &FakeDataStore,
     concurrency_level: usize,
-) -> (Vec<(StateKey, Option<WriteOp>)>, BenchmarkInfo) {
+) -> (Vec<(StateKey, Option<Vec<u8>>)>, BenchmarkInfo) {
     let pe = ParallelExecutor::<PreprocessedTransaction, AptosVMWrapper<FakeDataStore>>::new(
         concurrency_level,
     );
+    #[cfg(not(feature = "benchmark"))]
+    let pe = pe.with_watchdog(crate::test_utils::WATCHDOG_INTERVAL);
     let total_time = Instant::now();
-    let (output, execute, collect) = pe.execute_transactions_benchmark(txns, state);
+    let (output, report) = pe
+        .execute_transactions_benchmark(txns, state)
+        .expect("parallel execute error");
     (
         output,
         BenchmarkInfo {
             total_time: total_time.elapsed(),
-            execute_time: Some(execute),
-            collect_time: Some(collect),
+            execute_time: Some(report.execute_time),
+            collect_time: Some(report.collect_time),
+            num_aborts: Some(report.num_aborts),
         },
     )
 }
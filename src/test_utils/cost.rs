@@ -0,0 +1,156 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use arc_swap::ArcSwap;
+use once_cell::sync::Lazy;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// simulated execution cost of a transaction,which is paid by cpu busy-work (unlike sleeping),
+/// so that wasted re-executions occupy cpu as they do with a real vm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CostModel {
+    /// same cost for each execution
+    Fixed(Duration),
+    /// cost uniformly distributed in `min..=max`,drawn from a fixed sequence which restarts by `set_cost_model`
+    Uniform {
+        /// minimum cost
+        min: Duration,
+        /// maximum cost
+        max: Duration,
+    },
+    /// cost proportional to accesses of execution
+    PerAccess {
+        /// cost of each execution
+        base: Duration,
+        /// cost of each read
+        per_read: Duration,
+        /// cost of each write
+        per_write: Duration,
+    },
+}
+impl Default for CostModel {
+    fn default() -> Self {
+        Self::Fixed(Duration::from_micros(100))
+    }
+}
+impl CostModel {
+    /// check that model is valid,panic otherwise
+    pub fn check(&self) {
+        if let Self::Uniform { min, max } = self {
+            assert!(min <= max, "min cost {:?} > max cost {:?}", min, max);
+        }
+    }
+    /// cost of the `draw`-th execution with given number of reads and writes,the same draw always costs the same
+    pub fn cost(&self, draw: u64, reads: usize, writes: usize) -> Duration {
+        match *self {
+            Self::Fixed(cost) => cost,
+            Self::Uniform { min, max } => StdRng::seed_from_u64(draw).gen_range(min..=max),
+            Self::PerAccess {
+                base,
+                per_read,
+                per_write,
+            } => base + per_read * reads as u32 + per_write * writes as u32,
+        }
+    }
+}
+/// cost model used by simulated vms
+static COST_MODEL: Lazy<ArcSwap<CostModel>> =
+    Lazy::new(|| ArcSwap::from_pointee(CostModel::default()));
+/// number of costs drawn since cost model is set
+static DRAWS: AtomicU64 = AtomicU64::new(0);
+/// busy-work iterations per microsecond,calibrated by `set_cost_model` (or on first charge if it's never called)
+static ITERATIONS_PER_MICRO: Lazy<f64> = Lazy::new(calibrate);
+
+/// set cost model used by simulated vms,e.g. before each benchmark,
+/// it also calibrates busy-work,so the first charge after it isn't slowed down by calibration.
+///
+/// panic if model is invalid (e.g. uniform cost with `min > max`)
+pub fn set_cost_model(model: CostModel) {
+    model.check();
+    Lazy::force(&ITERATIONS_PER_MICRO);
+    COST_MODEL.store(Arc::new(model));
+    DRAWS.store(0, Ordering::Relaxed);
+}
+/// current cost model
+pub fn cost_model() -> CostModel {
+    **COST_MODEL.load()
+}
+/// pay cost of execution with given number of reads and writes by busy-work
+pub fn charge(reads: usize, writes: usize) {
+    let draw = DRAWS.fetch_add(1, Ordering::Relaxed);
+    pay(cost_model().cost(draw, reads, writes));
+}
+/// busy-work for `cost`
+fn pay(cost: Duration) {
+    spin((cost.as_secs_f64() * 1e6 * *ITERATIONS_PER_MICRO) as u64);
+}
+/// result of busy-work,stored so that it can't be optimized out
+static SINK: AtomicU64 = AtomicU64::new(0);
+/// busy-work
+fn spin(iterations: u64) {
+    let mut x = 0x9e3779b97f4a7c15_u64;
+    for i in 0..iterations {
+        x = (x ^ i).wrapping_mul(0x100000001b3).rotate_left(5);
+    }
+    SINK.store(x, Ordering::Relaxed);
+}
+/// measure rate of busy-work,best of several rounds to skip warm-up and preemption
+fn calibrate() -> f64 {
+    const ITERATIONS: u64 = 1_000_000;
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            spin(ITERATIONS);
+            ITERATIONS as f64 / (start.elapsed().as_secs_f64() * 1e6).max(f64::EPSILON)
+        })
+        .fold(0.0, f64::max)
+}
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{pay, set_cost_model, CostModel};
+
+    #[test]
+    fn uniform() {
+        let (min, max) = (Duration::from_micros(10), Duration::from_micros(20));
+        let model = CostModel::Uniform { min, max };
+        for draw in 0..100 {
+            let cost = model.cost(draw, 1, 1);
+            assert!(min <= cost && cost <= max);
+            assert_eq!(cost, model.cost(draw, 1, 1));
+        }
+    }
+    #[test]
+    #[should_panic(expected = "min cost")]
+    fn invalid_uniform() {
+        set_cost_model(CostModel::Uniform {
+            min: Duration::from_micros(20),
+            max: Duration::from_micros(10),
+        });
+    }
+    #[test]
+    fn wall_time() {
+        let cost = Duration::from_millis(20);
+        // best of several rounds,other tests may preempt busy-work
+        let elapsed = (0..3)
+            .map(|_| {
+                let start = Instant::now();
+                pay(cost);
+                start.elapsed()
+            })
+            .min()
+            .expect("at least one round");
+        assert!(
+            cost / 2 <= elapsed && elapsed <= cost * 3,
+            "{:?} charged in {:?}",
+            cost,
+            elapsed
+        );
+    }
+}
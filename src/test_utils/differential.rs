@@ -0,0 +1,200 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    hash::Hash,
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::{
+    core::{Transaction, VM},
+    ParallelExecutor, ParallelExecutorError,
+};
+
+/// snapshot of block,keys changed by block with their final values,`None` indicates deletion
+pub type Snapshot<T> = Vec<(<T as Transaction>::Key, Option<<T as Transaction>::Value>)>;
+/// config of differential test
+#[derive(Clone, Debug)]
+pub struct DifferentialConfig {
+    /// number of txns of generated block
+    pub txns_num: usize,
+    /// parallel executions of the same block at each concurrency level
+    pub repeats: usize,
+    /// concurrency levels,each should be between 1 and number of CPUs
+    pub concurrency_levels: Vec<usize>,
+    /// watchdog interval of parallel executions,see `ParallelExecutor::with_watchdog`
+    pub watchdog: Option<Duration>,
+}
+impl Default for DifferentialConfig {
+    /// 1000 txns,3 repeats at every concurrency level,with watchdog of tests
+    fn default() -> Self {
+        Self {
+            txns_num: 1_000,
+            repeats: 3,
+            concurrency_levels: (1..=num_cpus::get()).collect(),
+            watchdog: Some(super::WATCHDOG_INTERVAL),
+        }
+    }
+}
+/// divergence found by differential test
+#[derive(Debug, Error)]
+pub enum DifferentialError {
+    /// parallel execution failed
+    #[error("{case} at concurrency level {concurrency_level}: {source}")]
+    Execute {
+        /// block being executed
+        case: &'static str,
+        /// concurrency level of parallel execution
+        concurrency_level: usize,
+        /// error of parallel executor
+        source: ParallelExecutorError,
+    },
+    /// parallel snapshot differs from sequential one,carry the first diverging key
+    #[error("{case} at concurrency level {concurrency_level} (run {run}): key {key} expected {expected},got {actual}")]
+    Mismatch {
+        /// block being executed
+        case: &'static str,
+        /// concurrency level of parallel execution
+        concurrency_level: usize,
+        /// index of repeated run
+        run: usize,
+        /// diverging key
+        key: String,
+        /// value from sequential execution,`missing` if key is not changed
+        expected: String,
+        /// value from parallel execution,`missing` if key is not changed
+        actual: String,
+    },
+}
+/// run the standard battery of differential tests of `V` against a sequential reference executor.
+///
+/// `parameter` creates vms reading `base`,`sequential` executes block on `base` and returns its snapshot,
+/// `generate` generates block with given number of txns.
+/// empty block,single-transaction block and generated block are executed in parallel repeatedly at every concurrency level,
+/// each parallel snapshot should be the same as sequential one (order of keys doesn't matter).
+pub fn differential_test<V, S, R, G>(
+    parameter: V::Parameter,
+    base: &S,
+    sequential: R,
+    generate: G,
+    config: &DifferentialConfig,
+) -> Result<(), DifferentialError>
+where
+    V: VM,
+    <V::T as Transaction>::Value: PartialEq,
+    R: Fn(&[V::T], &S) -> Snapshot<V::T>,
+    G: FnMut(usize) -> Vec<V::T>,
+{
+    differential_test_with(
+        base,
+        sequential,
+        generate,
+        |txns, concurrency_level| {
+            let mut executor = ParallelExecutor::<V::T, V>::new(concurrency_level);
+            if let Some(interval) = config.watchdog {
+                executor = executor.with_watchdog(interval);
+            }
+            executor.execute_transactions(txns, parameter.clone())
+        },
+        config,
+    )
+}
+/// same battery as `differential_test`,but each block is executed by `execute` at given concurrency level,
+/// so that other entries of `ParallelExecutor` (or its configs) can be tested,`config.watchdog` is up to `execute`.
+pub fn differential_test_with<T, S, R, G, E>(
+    base: &S,
+    sequential: R,
+    mut generate: G,
+    mut execute: E,
+    config: &DifferentialConfig,
+) -> Result<(), DifferentialError>
+where
+    T: Transaction,
+    T::Value: PartialEq,
+    R: Fn(&[T], &S) -> Snapshot<T>,
+    G: FnMut(usize) -> Vec<T>,
+    E: FnMut(&[T], usize) -> Result<Snapshot<T>, ParallelExecutorError>,
+{
+    let blocks = [
+        ("empty block", Vec::new()),
+        ("single-transaction block", generate(1)),
+        ("generated block", generate(config.txns_num)),
+    ];
+    for (case, txns) in &blocks {
+        let expected = sequential(txns, base);
+        for concurrency_level in &config.concurrency_levels {
+            let concurrency_level = *concurrency_level;
+            for run in 0..config.repeats {
+                let actual = execute(txns, concurrency_level).map_err(|source| {
+                    DifferentialError::Execute {
+                        case,
+                        concurrency_level,
+                        source,
+                    }
+                })?;
+                if let Some((key, expected, actual)) = diverge::<T>(&expected, &actual) {
+                    return Err(DifferentialError::Mismatch {
+                        case,
+                        concurrency_level,
+                        run,
+                        key,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+/// snapshot of sequential execution from write sets of txns in order
+pub fn snapshot_of_write_sets<K, V, I>(write_sets: I) -> Vec<(K, Option<V>)>
+where
+    K: Eq + Hash + Clone,
+    I: IntoIterator<Item = Vec<(K, V)>>,
+{
+    let mut snapshot: Vec<(K, Option<V>)> = Vec::new();
+    let mut index: HashMap<K, usize> = HashMap::new();
+    for (k, v) in write_sets.into_iter().flatten() {
+        match index.get(&k) {
+            Some(i) => snapshot[*i].1 = Some(v),
+            None => {
+                index.insert(k.clone(), snapshot.len());
+                snapshot.push((k, Some(v)));
+            }
+        }
+    }
+    snapshot
+}
+/// first diverging key of snapshots,keys of `expected` are checked in order,then extra keys of `actual`
+fn diverge<T>(expected: &Snapshot<T>, actual: &Snapshot<T>) -> Option<(String, String, String)>
+where
+    T: Transaction,
+    T::Value: PartialEq,
+{
+    fn describe<V: Debug>(v: Option<&Option<V>>) -> String {
+        match v {
+            Some(v) => format!("{:?}", v),
+            None => "missing".to_string(),
+        }
+    }
+    let actual_map: HashMap<_, _> = actual.iter().map(|(k, v)| (k, v)).collect();
+    for (k, v) in expected {
+        let a = actual_map.get(k).copied();
+        if a != Some(v) {
+            return Some((format!("{:?}", k), describe(Some(v)), describe(a)));
+        }
+    }
+    let expected_keys: HashSet<_> = expected.iter().map(|(k, _)| k).collect();
+    actual
+        .iter()
+        .find(|(k, _)| !expected_keys.contains(k))
+        .map(|(k, v)| {
+            (
+                format!("{:?}", k),
+                describe::<T::Value>(None),
+                describe(Some(v)),
+            )
+        })
+}
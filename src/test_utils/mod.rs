@@ -3,12 +3,25 @@ use std::time::Duration;
 /// smart contract (move-based) transfer transaction
 #[cfg(feature = "aptos_transfer")]
 pub mod aptos;
+/// cpu-bound execution cost of simulated vms
+pub mod cost;
+/// differential testing of vms against sequential reference
+pub mod differential;
 // log utils
 mod log;
+/// no-op transactions driven by scheduler alone
+pub mod noop;
+/// declarative test transactions,their vm and proptest strategies
+pub mod ops;
+/// statistical benchmark reports
+#[cfg(feature = "report")]
+pub mod report;
 /// simulated transfer transaction
 #[cfg(feature = "simulated_transfer")]
 pub mod simulated;
 
+/// watchdog interval used by tests,parallel execution aborts if no progress within it
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_secs(10);
 /// info during execution process
 #[derive(Debug, Default)]
 pub struct BenchmarkInfo {
@@ -18,6 +31,8 @@ pub struct BenchmarkInfo {
     pub execute_time: Option<Duration>,
     /// collect walltime
     pub collect_time: Option<Duration>,
+    /// number of aborted incarnations
+    pub num_aborts: Option<usize>,
 }
 ///
 #[derive(Debug, Default)]
@@ -29,38 +44,34 @@ impl BenchmarkInfos {
     ///
     pub fn add_info(&mut self, info: BenchmarkInfo) {
         self.data.push(info);
-        let mut t = Duration::ZERO;
-        let mut e = Duration::ZERO;
-        let mut c = Duration::ZERO;
-        self.data.iter().for_each(
-            |BenchmarkInfo {
-                 total_time,
-                 execute_time,
-                 collect_time,
-             }| {
-                t += *total_time;
-                if let Some(execute_time) = execute_time {
-                    e += *execute_time;
-                }
-                if let Some(collect_time) = collect_time {
-                    c += *collect_time;
-                }
-            },
-        );
+        let t: Duration = self.data.iter().map(|info| info.total_time).sum();
+        // optional fields are averaged over samples reporting them
+        let e: Vec<_> = self
+            .data
+            .iter()
+            .filter_map(|info| info.execute_time)
+            .collect();
+        let c: Vec<_> = self
+            .data
+            .iter()
+            .filter_map(|info| info.collect_time)
+            .collect();
+        let a: Vec<_> = self
+            .data
+            .iter()
+            .filter_map(|info| info.num_aborts)
+            .collect();
         self.mean = BenchmarkInfo {
             total_time: t / self.data.len() as u32,
-            execute_time: if e == Duration::ZERO {
-                None
-            } else {
-                Some(e / self.data.len() as u32)
-            },
-            collect_time: if c == Duration::ZERO {
-                None
-            } else {
-                Some(c / self.data.len() as u32)
-            },
+            execute_time: (!e.is_empty()).then(|| e.iter().sum::<Duration>() / e.len() as u32),
+            collect_time: (!c.is_empty()).then(|| c.iter().sum::<Duration>() / c.len() as u32),
+            num_aborts: (!a.is_empty()).then(|| a.iter().sum::<usize>() / a.len()),
         }
     }
+    /// samples added since last clear
+    pub fn samples(&self) -> &[BenchmarkInfo] {
+        &self.data
+    }
     ///
     pub fn clear_infos(&mut self) {
         self.data.clear();
@@ -68,8 +79,36 @@ impl BenchmarkInfos {
     ///
     pub fn mean(&self) -> String {
         format!(
-            "Mean total time:{:?},Mean execute time:{:?},Mean collect time:{:?}",
-            self.mean.total_time, self.mean.execute_time, self.mean.collect_time
+            "Mean total time:{:?},Mean execute time:{:?},Mean collect time:{:?},Mean aborts:{:?}",
+            self.mean.total_time,
+            self.mean.execute_time,
+            self.mean.collect_time,
+            self.mean.num_aborts
         )
     }
 }
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{BenchmarkInfo, BenchmarkInfos};
+
+    #[test]
+    fn mean_of_reported_samples() {
+        let mut infos = BenchmarkInfos::default();
+        infos.add_info(BenchmarkInfo {
+            total_time: Duration::from_millis(2),
+            execute_time: Some(Duration::from_millis(1)),
+            collect_time: None,
+            num_aborts: Some(10),
+        });
+        infos.add_info(BenchmarkInfo {
+            total_time: Duration::from_millis(4),
+            ..Default::default()
+        });
+        assert_eq!(
+            infos.mean(),
+            "Mean total time:3ms,Mean execute time:Some(1ms),Mean collect time:None,Mean aborts:Some(10)"
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use crate::scheduler::{Scheduler, SchedulerTask};
+
+/// execute a block of no-op transactions by `num_threads` workers,
+/// first incarnation of every `abort_every`-th transaction is aborted by validation.
+///
+/// there's no vm or mvmemory,so walltime is the overhead of scheduling,used by `scheduler` bench.
+/// returns number of aborted incarnations
+pub fn execute_noop_block(block_size: usize, num_threads: usize, abort_every: usize) -> usize {
+    let scheduler = Scheduler::new(block_size);
+    crossbeam::scope(|s| {
+        for _ in 0..num_threads {
+            s.spawn(|_| loop {
+                let mut task = scheduler.next_task();
+                loop {
+                    task = match task {
+                        SchedulerTask::Execution((txn_idx, incarnation), _, guard) => {
+                            scheduler.finish_execution(txn_idx, incarnation, false, guard)
+                        }
+                        SchedulerTask::Validation((txn_idx, incarnation), guard) => {
+                            let aborted = incarnation == 0
+                                && abort_every > 0
+                                && txn_idx % abort_every == 0
+                                && scheduler.abort(txn_idx, incarnation);
+                            scheduler.finish_validation(txn_idx, aborted, guard)
+                        }
+                        SchedulerTask::NoTask => break,
+                        SchedulerTask::Done => return,
+                    }
+                }
+            });
+        }
+    })
+    .expect("noop worker panicked");
+    scheduler.num_aborts()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::execute_noop_block;
+
+    #[test]
+    fn noop_block() {
+        assert_eq!(execute_noop_block(1000, 4, 10), 100);
+        assert_eq!(execute_noop_block(1000, 4, 0), 0);
+    }
+}
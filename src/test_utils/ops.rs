@@ -0,0 +1,523 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash, marker::PhantomData};
+
+use proptest::{collection::vec, prelude::*};
+
+use crate::{
+    core::{Event, Transaction, TransactionOutput, WriteOp, VM},
+    mvmemory::{MVMemoryView, ReadResult},
+    storage::{BaseView, InMemoryStorage},
+};
+
+/// operation of ops transaction
+#[derive(Clone, Debug)]
+pub enum Op<K> {
+    /// read key
+    Read(K),
+    /// write constant to key
+    Write(K, u64),
+    /// write `f(values of reads)` to key,`None` for missing (or deleted) values
+    Apply {
+        /// written key
+        key: K,
+        /// read keys
+        reads: Vec<K>,
+        /// function of read values
+        f: fn(Vec<Option<u64>>) -> u64,
+    },
+    /// delete key
+    Delete(K),
+    /// emit event with given payload
+    Emit(u64),
+}
+impl<K> Op<K> {
+    /// write sum of read values (missing as 0) plus 1 to key
+    pub fn sum(key: K, reads: Vec<K>) -> Self {
+        Self::Apply {
+            key,
+            reads,
+            f: |values| {
+                values
+                    .into_iter()
+                    .fold(1, |sum, v| sum.wrapping_add(v.unwrap_or(0)))
+            },
+        }
+    }
+}
+/// declarative test transaction,whose body is a list of ops executed in order,
+/// used to test scheduler for arbitrary conflict patterns independent of any real vm.
+///
+/// ops read writes of earlier ops of the same transaction.
+#[derive(Clone, Debug)]
+pub struct OpsTransaction<K> {
+    /// body of transaction
+    pub ops: Vec<Op<K>>,
+}
+impl<K> Transaction for OpsTransaction<K>
+where
+    K: Eq + Hash + Clone + Send + Sync + Debug,
+{
+    type Key = K;
+
+    type Value = u64;
+}
+/// output of ops transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpsOutput<K> {
+    /// value of each read,in order of ops
+    pub reads: Vec<(K, Option<u64>)>,
+    /// last write of each written key,in order of first write,
+    /// `WriteOp::Create` if key doesn't exist before transaction
+    pub writes: Vec<(K, WriteOp<u64>)>,
+    /// events of `Op::Emit`,in order of ops
+    pub events: Vec<Event>,
+    /// number of ops
+    pub gas_used: u64,
+}
+impl<K> TransactionOutput for OpsOutput<K>
+where
+    K: Eq + Hash + Clone + Send + Sync + Debug,
+{
+    type T = OpsTransaction<K>;
+
+    fn get_write_set(&self) -> Vec<(K, WriteOp<u64>)> {
+        self.writes.clone()
+    }
+
+    /// one unit of gas per op
+    fn get_gas_used(&self) -> u64 {
+        self.gas_used
+    }
+
+    fn get_events(&self) -> Vec<Event> {
+        self.events.clone()
+    }
+}
+impl<K> OpsTransaction<K>
+where
+    K: Eq + Hash + Clone,
+{
+    /// execute ops with given reader of state (latest value before this transaction).
+    ///
+    /// kind of each write depends on whether key exists before transaction,
+    /// which is read at the end if it's not read by ops before being written.
+    pub fn execute(&self, read: impl Fn(&K) -> Option<u64>) -> OpsOutput<K> {
+        let mut reads = Vec::new();
+        let mut writes: Vec<(K, Option<u64>)> = Vec::new();
+        let mut written: HashMap<K, usize> = HashMap::new();
+        // values before transaction of keys read from state
+        let mut prior: HashMap<K, Option<u64>> = HashMap::new();
+        let mut events = Vec::new();
+        for op in &self.ops {
+            let mut read_key = |k: &K| {
+                let v = match written.get(k) {
+                    Some(i) => writes[*i].1,
+                    None => *prior.entry(k.clone()).or_insert_with(|| read(k)),
+                };
+                reads.push((k.clone(), v));
+                v
+            };
+            let (key, value) = match op {
+                Op::Read(key) => {
+                    read_key(key);
+                    continue;
+                }
+                Op::Write(key, value) => (key, Some(*value)),
+                Op::Apply { key, reads, f } => (key, Some(f(reads.iter().map(read_key).collect()))),
+                Op::Delete(key) => (key, None),
+                Op::Emit(data) => {
+                    events.push(Event {
+                        type_tag: "ops".to_string(),
+                        data: data.to_le_bytes().to_vec(),
+                    });
+                    continue;
+                }
+            };
+            match written.get(key) {
+                Some(i) => writes[*i].1 = value,
+                None => {
+                    written.insert(key.clone(), writes.len());
+                    writes.push((key.clone(), value));
+                }
+            }
+        }
+        let writes = writes
+            .into_iter()
+            .map(|(k, v)| {
+                let op = match v {
+                    Some(v) if prior.get(&k).map_or_else(|| read(&k), |v| *v).is_none() => {
+                        WriteOp::Create(v)
+                    }
+                    Some(v) => WriteOp::Modify(v),
+                    None => WriteOp::Delete,
+                };
+                (k, op)
+            })
+            .collect();
+        OpsOutput {
+            reads,
+            writes,
+            events,
+            gas_used: self.ops.len() as u64,
+        }
+    }
+}
+/// vm of ops transactions,reading base state from `S`
+pub struct OpsVM<'a, K, S = InMemoryStorage<K, u64>>(&'a S, PhantomData<fn() -> K>);
+impl<'a, K, S> VM for OpsVM<'a, K, S>
+where
+    K: Eq + Hash + Clone + Send + Sync + Debug,
+    S: BaseView<K, u64>,
+{
+    type T = OpsTransaction<K>;
+
+    type Output = OpsOutput<K>;
+
+    type Error = ();
+
+    type Parameter = &'a S;
+
+    fn new(argument: Self::Parameter) -> Self {
+        Self(argument, PhantomData)
+    }
+
+    fn execute_transaction(
+        &self,
+        txn: &Self::T,
+        view: &MVMemoryView<K, u64>,
+    ) -> Result<Self::Output, Self::Error> {
+        Ok(txn.execute(|k| match view.read(k) {
+            ReadResult::Value(v) => Some(*v),
+            ReadResult::Deleted => None,
+            ReadResult::NotFound => self.0.read(k),
+        }))
+    }
+}
+/// sequential execute ops transactions on storage,return output of each transaction and the final state
+pub fn ops_sequential_execute<K>(
+    txns: &[OpsTransaction<K>],
+    storage: &InMemoryStorage<K, u64>,
+) -> (Vec<OpsOutput<K>>, InMemoryStorage<K, u64>)
+where
+    K: Eq + Hash + Clone + Sync,
+{
+    let mut state = storage.state().clone();
+    let outputs = txns
+        .iter()
+        .map(|txn| {
+            let output = txn.execute(|k| state.get(k).copied());
+            for (k, op) in &output.writes {
+                match op.as_ref().into_value() {
+                    Some(v) => state.insert(k.clone(), *v),
+                    None => state.remove(k),
+                };
+            }
+            output
+        })
+        .collect();
+    (outputs, state.into_iter().collect())
+}
+/// strategy of op on keys `0..keys`
+pub fn op_strategy(keys: usize) -> impl Strategy<Value = Op<usize>> {
+    let key = 0..keys;
+    prop_oneof![
+        3 => key.clone().prop_map(Op::Read),
+        2 => (key.clone(), any::<u64>()).prop_map(|(k, v)| Op::Write(k, v)),
+        2 => (key.clone(), vec(key.clone(), 1..4)).prop_map(|(k, reads)| Op::sum(k, reads)),
+        1 => key.prop_map(Op::Delete),
+    ]
+}
+/// strategy of ops transaction with up to `max_ops` ops on keys `0..keys`
+pub fn ops_transaction_strategy(
+    keys: usize,
+    max_ops: usize,
+) -> impl Strategy<Value = OpsTransaction<usize>> {
+    vec(op_strategy(keys), 1..=max_ops).prop_map(|ops| OpsTransaction { ops })
+}
+/// strategy of block with up to `max_txns` ops transactions,fewer keys are more contended
+pub fn ops_block_strategy(
+    keys: usize,
+    max_txns: usize,
+    max_ops: usize,
+) -> impl Strategy<Value = Vec<OpsTransaction<usize>>> {
+    vec(ops_transaction_strategy(keys, max_ops), 0..=max_txns)
+}
+/// strategy of genesis storage with values on some of keys `0..keys`
+pub fn storage_strategy(keys: usize) -> impl Strategy<Value = InMemoryStorage<usize, u64>> {
+    vec(proptest::option::of(any::<u64>()), keys).prop_map(|values| {
+        values
+            .into_iter()
+            .enumerate()
+            .filter_map(|(k, v)| v.map(|v| (k, v)))
+            .collect()
+    })
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use proptest::prelude::*;
+
+    use super::{
+        ops_block_strategy, ops_sequential_execute, storage_strategy, Op, OpsOutput,
+        OpsTransaction, OpsVM,
+    };
+    use crate::{
+        core::{WriteOp, VM},
+        mvmemory::{MVMemoryView, ReadResult},
+        storage::{BaseView, InMemoryStorage, Storage},
+        types::TxnIndex,
+        ExecutorContext, ParallelExecutor, ParallelExecutorError, StateChange,
+    };
+
+    /// vm failing to execute the transaction at given index
+    struct FailingVM(TxnIndex);
+    impl VM for FailingVM {
+        type T = OpsTransaction<usize>;
+
+        type Output = OpsOutput<usize>;
+
+        type Error = &'static str;
+
+        type Parameter = TxnIndex;
+
+        fn new(parameter: Self::Parameter) -> Self {
+            Self(parameter)
+        }
+
+        fn execute_transaction(
+            &self,
+            txn: &Self::T,
+            view: &MVMemoryView<usize, u64>,
+        ) -> Result<Self::Output, Self::Error> {
+            if view.txn_idx() == self.0 {
+                return Err("out of gas");
+            }
+            Ok(txn.execute(|_| None))
+        }
+    }
+
+    /// vm failing transaction whose counter at key 0 isn't its index,
+    /// which only happens to incarnations reading stale values of lower transactions
+    struct StrictVM;
+    impl VM for StrictVM {
+        type T = OpsTransaction<usize>;
+
+        type Output = OpsOutput<usize>;
+
+        type Error = &'static str;
+
+        type Parameter = ();
+
+        fn new(_: Self::Parameter) -> Self {
+            Self
+        }
+
+        fn execute_transaction(
+            &self,
+            txn: &Self::T,
+            view: &MVMemoryView<usize, u64>,
+        ) -> Result<Self::Output, Self::Error> {
+            let read = |k: &usize| match view.read(k) {
+                ReadResult::Value(v) => Some(*v),
+                _ => None,
+            };
+            if read(&0).unwrap_or(0) != view.txn_idx() as u64 {
+                return Err("stale counter");
+            }
+            Ok(txn.execute(read))
+        }
+    }
+
+    /// vm writing a different value on each execution
+    struct UnstableVM;
+    static EXECUTIONS: AtomicU64 = AtomicU64::new(0);
+    impl VM for UnstableVM {
+        type T = OpsTransaction<usize>;
+
+        type Output = OpsOutput<usize>;
+
+        type Error = ();
+
+        type Parameter = ();
+
+        fn new(_: Self::Parameter) -> Self {
+            Self
+        }
+
+        fn execute_transaction(
+            &self,
+            _: &Self::T,
+            view: &MVMemoryView<usize, u64>,
+        ) -> Result<Self::Output, Self::Error> {
+            Ok(OpsOutput {
+                reads: vec![],
+                writes: vec![(
+                    view.txn_idx(),
+                    WriteOp::Modify(EXECUTIONS.fetch_add(1, Ordering::Relaxed)),
+                )],
+                events: vec![],
+                gas_used: 1,
+            })
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+        #[test]
+        fn ops_parallel_execute(
+            storage in storage_strategy(8),
+            txns in ops_block_strategy(8, 50, 5),
+        ) {
+            let (_, expected) = ops_sequential_execute(&txns, &storage);
+            let snapshot = ParallelExecutor::<_, OpsVM<usize>>::new(num_cpus::get())
+                .with_watchdog(crate::test_utils::WATCHDOG_INTERVAL)
+                .execute_transactions(&txns, &storage)
+                .unwrap();
+            let mut actual: InMemoryStorage<_, _> = storage.clone();
+            actual.commit(&snapshot).unwrap();
+            prop_assert_eq!(actual.state(), expected.state());
+        }
+    }
+    fn change(key: usize, before: Option<u64>, after: WriteOp<u64>) -> StateChange<usize, u64> {
+        StateChange { key, before, after }
+    }
+    #[test]
+    fn ops_executed_block() {
+        let storage: InMemoryStorage<usize, u64> = [(0, 100), (2, 9)].into_iter().collect();
+        // each transaction increments key 0,transaction 2 also writes key 1,transaction 3 deletes key 2,
+        // transactions 1 and 3 emit events
+        let mut txns = vec![
+            OpsTransaction {
+                ops: vec![Op::sum(0, vec![0])],
+            };
+            4
+        ];
+        txns[2].ops.push(Op::Write(1, 7));
+        txns[3].ops.push(Op::Emit(3));
+        txns[3].ops.push(Op::Delete(2));
+        txns[1].ops.push(Op::Emit(1));
+        let executor = ParallelExecutor::<_, OpsVM<usize>>::new(num_cpus::get())
+            .with_watchdog(crate::test_utils::WATCHDOG_INTERVAL);
+        let block = executor.execute(&txns, &storage).unwrap();
+        assert_eq!(block.value_at(&0, 0), None);
+        for txn_idx in 1..=txns.len() {
+            let (writer, value) = block.value_at(&0, txn_idx).unwrap();
+            assert_eq!(
+                (writer, value.map(|v| *v)),
+                (txn_idx - 1, Some(100 + txn_idx as u64))
+            );
+        }
+        assert_eq!(block.value_at(&1, 2), None);
+        assert_eq!(block.value_at(&1, 3).map(|(writer, _)| writer), Some(2));
+        assert_eq!(block.value_at(&2, 4), Some((3, None)));
+        // simulated transaction sees writes below its position only
+        let query = OpsTransaction {
+            ops: vec![Op::Read(0), Op::Read(1), Op::Read(2)],
+        };
+        let output = block.simulate(&query, 2).unwrap();
+        assert_eq!(output.reads, vec![(0, Some(102)), (1, None), (2, Some(9))]);
+        // deleted key is not read from storage
+        let output = block.simulate(&query, 4).unwrap();
+        assert_eq!(output.reads, vec![(0, Some(104)), (1, Some(7)), (2, None)]);
+        assert_eq!(block.gas_used(), 8);
+        let events: Vec<_> = block
+            .events()
+            .into_iter()
+            .map(|(txn_idx, event)| (txn_idx, event.data))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (1, 1u64.to_le_bytes().to_vec()),
+                (3, 3u64.to_le_bytes().to_vec())
+            ]
+        );
+        let changes = block.changes(|k| storage.read(k));
+        assert_eq!(changes[0], vec![change(0, Some(100), WriteOp::Modify(101))]);
+        // key 1 doesn't exist before transaction 2
+        assert_eq!(
+            changes[2],
+            vec![
+                change(0, Some(102), WriteOp::Modify(103)),
+                change(1, None, WriteOp::Create(7))
+            ]
+        );
+        assert_eq!(
+            changes[3],
+            vec![
+                change(0, Some(103), WriteOp::Modify(104)),
+                change(2, Some(9), WriteOp::Delete)
+            ]
+        );
+        let mut snapshot = block.collect();
+        snapshot.sort_unstable();
+        assert_eq!(snapshot, vec![(0, Some(104)), (1, Some(7)), (2, None)]);
+    }
+    #[test]
+    fn ops_vm_error() {
+        let txns = vec![
+            OpsTransaction {
+                ops: vec![Op::sum(0, vec![0])],
+            };
+            10
+        ];
+        let result = ParallelExecutor::<_, FailingVM>::new(num_cpus::get())
+            .with_watchdog(crate::test_utils::WATCHDOG_INTERVAL)
+            .execute_transactions(&txns, 3);
+        match result {
+            Err(ParallelExecutorError::Execution { txn_idx, error }) => {
+                assert_eq!((txn_idx, error.as_str()), (3, "\"out of gas\""));
+            }
+            _ => panic!("vm error should fail block"),
+        }
+    }
+    #[test]
+    fn ops_speculative_vm_error() {
+        let txns = vec![
+            OpsTransaction {
+                ops: vec![Op::sum(0, vec![0])],
+            };
+            100
+        ];
+        // errors of incarnations reading stale counters are gone once they are re-executed
+        let snapshot = ParallelExecutor::<_, StrictVM>::new(num_cpus::get())
+            .with_watchdog(crate::test_utils::WATCHDOG_INTERVAL)
+            .execute_transactions(&txns, ())
+            .unwrap();
+        assert_eq!(snapshot, vec![(0, Some(100))]);
+    }
+    #[test]
+    fn ops_nondeterministic_vm() {
+        let txns = vec![OpsTransaction { ops: vec![] }; 10];
+        let result = ParallelExecutor::<_, UnstableVM>::new(num_cpus::get())
+            .with_watchdog(crate::test_utils::WATCHDOG_INTERVAL)
+            .verify_determinism(&txns, (), 3);
+        match result {
+            Err(ParallelExecutorError::NonDeterministic {
+                run, kind, txn_idx, ..
+            }) => assert_eq!((run, kind, txn_idx), (1, "write set", Some(0))),
+            _ => panic!("diverging runs should be detected"),
+        }
+    }
+    #[test]
+    fn ops_context_after_failure() {
+        let storage: InMemoryStorage<usize, u64> = [(0, 100)].into_iter().collect();
+        let txns = vec![
+            OpsTransaction {
+                ops: vec![Op::sum(0, vec![0]), Op::Write(1, 7)],
+            };
+            10
+        ];
+        let mut context = ExecutorContext::new();
+        let failed = ParallelExecutor::<_, FailingVM>::new(num_cpus::get())
+            .execute_transactions_with_context(&mut context, &txns, 5);
+        assert!(failed.is_err());
+        // versions of failed block don't leak into next one
+        let txns = &txns[..2];
+        let mut snapshot = ParallelExecutor::<_, OpsVM<usize>>::new(num_cpus::get())
+            .execute_transactions_with_context(&mut context, txns, &storage)
+            .unwrap();
+        snapshot.sort_unstable();
+        assert_eq!(snapshot, vec![(0, Some(102)), (1, Some(7))]);
+    }
+}
@@ -0,0 +1,250 @@
+use std::{fmt::Write as _, fs, io, path::Path, time::Duration};
+
+use serde::{Serialize, Serializer};
+
+use super::BenchmarkInfos;
+
+/// serialize duration in microseconds
+fn micros<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_micros())
+}
+
+/// statistics of total time of benchmark samples,times are serialized in microseconds
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BenchmarkStats {
+    /// number of samples
+    pub samples: usize,
+    /// mean
+    #[serde(rename = "mean_us", serialize_with = "micros")]
+    pub mean: Duration,
+    /// standard deviation
+    #[serde(rename = "stddev_us", serialize_with = "micros")]
+    pub stddev: Duration,
+    /// median
+    #[serde(rename = "p50_us", serialize_with = "micros")]
+    pub p50: Duration,
+    /// 90th percentile
+    #[serde(rename = "p90_us", serialize_with = "micros")]
+    pub p90: Duration,
+    /// 99th percentile
+    #[serde(rename = "p99_us", serialize_with = "micros")]
+    pub p99: Duration,
+    /// mean number of aborted incarnations,`None` if not reported
+    pub mean_aborts: Option<f64>,
+}
+impl BenchmarkStats {
+    /// statistics of samples,`None` if there is no sample
+    pub fn new(infos: &BenchmarkInfos) -> Option<Self> {
+        let samples = infos.samples();
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let mut times: Vec<f64> = samples
+            .iter()
+            .map(|info| info.total_time.as_secs_f64())
+            .collect();
+        times.sort_by(|a, b| a.partial_cmp(b).expect("time is not nan"));
+        let mean = times.iter().sum::<f64>() / n;
+        let variance = times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / n;
+        // nearest rank
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0 * n).ceil() as usize).max(1);
+            Duration::from_secs_f64(times[rank - 1])
+        };
+        let aborts: Vec<usize> = samples.iter().filter_map(|info| info.num_aborts).collect();
+        Some(Self {
+            samples: samples.len(),
+            mean: Duration::from_secs_f64(mean),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p99: percentile(99.0),
+            mean_aborts: if aborts.is_empty() {
+                None
+            } else {
+                Some(aborts.iter().sum::<usize>() as f64 / aborts.len() as f64)
+            },
+        })
+    }
+}
+/// workload parameters of benchmark
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct BenchmarkParameters {
+    /// number of accounts (keys),0 if not applicable
+    pub accounts: usize,
+    /// number of transactions of block
+    pub txns: usize,
+    /// number of cpus (concurrency level)
+    pub cpus: usize,
+}
+/// single benchmark of report,serialized as flat object
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BenchmarkRecord {
+    /// name of benchmark
+    pub name: String,
+    /// workload parameters
+    #[serde(flatten)]
+    pub parameters: BenchmarkParameters,
+    /// statistics of samples
+    #[serde(flatten)]
+    pub stats: BenchmarkStats,
+    /// transactions per second,computed from mean
+    pub tps: f64,
+    /// mean of baseline over mean of this benchmark,`None` if there is no baseline with same accounts and txns
+    pub speedup: Option<f64>,
+}
+/// report of benchmarks,which can be exported as json or csv to track regressions across commits
+#[derive(Clone, Debug, Default)]
+pub struct BenchmarkReport {
+    records: Vec<BenchmarkRecord>,
+    /// index of baseline records
+    baselines: Vec<usize>,
+}
+impl BenchmarkReport {
+    /// record samples of benchmark,return `false` if there is no sample
+    pub fn add(
+        &mut self,
+        name: &str,
+        parameters: BenchmarkParameters,
+        infos: &BenchmarkInfos,
+    ) -> bool {
+        let stats = match BenchmarkStats::new(infos) {
+            Some(stats) => stats,
+            None => return false,
+        };
+        // latest baseline of same workload
+        let speedup = self
+            .baselines
+            .iter()
+            .rev()
+            .map(|i| &self.records[*i])
+            .find(|baseline| {
+                (baseline.parameters.accounts, baseline.parameters.txns)
+                    == (parameters.accounts, parameters.txns)
+            })
+            .map(|baseline| baseline.stats.mean.as_secs_f64() / stats.mean.as_secs_f64());
+        self.records.push(BenchmarkRecord {
+            name: name.to_string(),
+            parameters,
+            tps: parameters.txns as f64 / stats.mean.as_secs_f64(),
+            stats,
+            speedup,
+        });
+        true
+    }
+    /// record samples of baseline (e.g. sequential execution),
+    /// speedup of later benchmarks with same accounts and txns is computed against it
+    pub fn add_baseline(
+        &mut self,
+        name: &str,
+        parameters: BenchmarkParameters,
+        infos: &BenchmarkInfos,
+    ) -> bool {
+        let added = self.add(name, parameters, infos);
+        if added {
+            self.baselines.push(self.records.len() - 1);
+        }
+        added
+    }
+    /// recorded benchmarks
+    pub fn records(&self) -> &[BenchmarkRecord] {
+        &self.records
+    }
+    /// report as json array of records,times are in microseconds
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.records).expect("records are serializable")
+    }
+    /// report as csv with header,times are in microseconds,missing values are empty
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "name,accounts,txns,cpus,samples,mean_us,stddev_us,p50_us,p90_us,p99_us,mean_aborts,tps,speedup\n",
+        );
+        let optional = |v: Option<f64>| v.map(|v| format!("{:.3}", v)).unwrap_or_default();
+        for record in &self.records {
+            let _ = writeln!(
+                csv,
+                "\"{}\",{},{},{},{},{},{},{},{},{},{},{:.3},{}",
+                record.name.replace('"', "\"\""),
+                record.parameters.accounts,
+                record.parameters.txns,
+                record.parameters.cpus,
+                record.stats.samples,
+                record.stats.mean.as_micros(),
+                record.stats.stddev.as_micros(),
+                record.stats.p50.as_micros(),
+                record.stats.p90.as_micros(),
+                record.stats.p99.as_micros(),
+                optional(record.stats.mean_aborts),
+                record.tps,
+                optional(record.speedup),
+            );
+        }
+        csv
+    }
+    /// export report to path,as json if its extension is `json`,otherwise as csv
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if path.extension().map_or(false, |ext| ext == "json") {
+            let writer = io::BufWriter::new(fs::File::create(path)?);
+            serde_json::to_writer_pretty(writer, &self.records).map_err(io::Error::from)
+        } else {
+            fs::write(path, self.to_csv())
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{BenchmarkParameters, BenchmarkReport};
+    use crate::test_utils::{BenchmarkInfo, BenchmarkInfos};
+
+    fn infos(millis: &[u64]) -> BenchmarkInfos {
+        let mut infos = BenchmarkInfos::default();
+        for ms in millis {
+            infos.add_info(BenchmarkInfo {
+                total_time: Duration::from_millis(*ms),
+                ..Default::default()
+            });
+        }
+        infos
+    }
+    #[test]
+    fn report() {
+        let parameters = BenchmarkParameters {
+            accounts: 10,
+            txns: 1_000,
+            cpus: 1,
+        };
+        let mut report = BenchmarkReport::default();
+        assert!(!report.add("empty", parameters, &infos(&[])));
+        report.add_baseline("sequential", parameters, &infos(&[100, 100]));
+        let samples: Vec<u64> = (1..=100).collect();
+        report.add("parallel", parameters, &infos(&samples));
+        let record = &report.records()[1];
+        assert_eq!(
+            (record.stats.p50, record.stats.p90, record.stats.p99),
+            (
+                Duration::from_millis(50),
+                Duration::from_millis(90),
+                Duration::from_millis(99)
+            )
+        );
+        assert_eq!(record.stats.mean, Duration::from_micros(50_500));
+        assert!((record.speedup.unwrap() - 100.0 / 50.5).abs() < 1e-9);
+        assert!((record.tps - 1_000.0 / 0.0505).abs() < 1e-6);
+        assert_eq!(report.to_csv().lines().count(), 3);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json[1]["name"], "parallel");
+        assert_eq!(json[1]["mean_us"], 50_500);
+        assert_eq!(json[0]["speedup"], serde_json::Value::Null);
+        // name is escaped
+        report.add("\"quoted\"\n", parameters, &infos(&[1]));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json[2]["name"], "\"quoted\"\n");
+    }
+}
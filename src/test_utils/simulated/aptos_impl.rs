@@ -79,9 +79,6 @@ impl<'a> ExecutorTask for ParallelVM<'a> {
         view: &MVHashMapView<<Self::T as Transaction>::Key, <Self::T as Transaction>::Value>,
         txn: &Self::T,
     ) -> ExecutionStatus<Self::Output, Self::Error> {
-        #[cfg(feature = "benchmark")]
-        std::thread::sleep(std::time::Duration::from_micros(100));
-
         let read = |key| match view.read(key) {
             ReadResult::Value(value) => (*value).0,
             ReadResult::None => *self.0.get(&key.0).expect("get error"),
@@ -98,6 +95,9 @@ impl<'a> ExecutorTask for ParallelVM<'a> {
         } else {
             vec![]
         };
+        // `to` is only read by successful transfer
+        #[cfg(feature = "benchmark")]
+        crate::test_utils::cost::charge(1 + output.len() / 2, output.len());
         ExecutionStatus::Success(TransferTransactionOutput(output))
     }
 }
@@ -118,6 +118,7 @@ pub fn aptos_parallel_execute(
                 total_time: total.elapsed(),
                 execute_time: Some(execute),
                 collect_time: Some(collect.elapsed()),
+                num_aborts: None,
             },
         )
     } else {
@@ -0,0 +1,238 @@
+use rand::{
+    distributions::{Uniform, WeightedIndex},
+    prelude::Distribution,
+    rngs::StdRng,
+    Rng, SeedableRng,
+};
+
+use super::{generate_ledger, Ledger, TransferTransaction};
+
+/// distribution of accounts accessed by generated txns,which tunes contention of block
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccountDistribution {
+    /// each account is equally likely
+    Uniform,
+    /// account `i` is accessed with probability proportional to `1 / (i + 1)^exponent`
+    Zipfian {
+        /// skew,0 is uniform,bigger is more skewed
+        exponent: f64,
+    },
+    /// `traffic` (0..=1) of accesses go to the first `keys` (0..=1) of accounts,
+    /// e.g. `traffic: 0.9,keys: 0.1` for 90% of traffic on 10% of accounts
+    Hotspot {
+        /// fraction of accesses on hot accounts
+        traffic: f64,
+        /// fraction of hot accounts
+        keys: f64,
+    },
+    /// every txn transfers to account 0,senders are uniform among other accounts
+    Sink,
+}
+impl AccountDistribution {
+    /// number of hot accounts of hotspot,at least one hot and one cold account
+    fn hot_num(keys: f64, accounts_num: usize) -> usize {
+        ((accounts_num as f64 * keys).ceil() as usize)
+            .max(1)
+            .min(accounts_num - 1)
+    }
+    /// panic if distribution can't sample two different accounts out of `accounts_num`,
+    /// in which case generating txns never ends
+    fn check(&self, accounts_num: usize) {
+        match *self {
+            Self::Uniform | Self::Sink => {}
+            Self::Zipfian { exponent } => {
+                assert!(
+                    exponent.is_finite() && exponent >= 0.0,
+                    "zipfian exponent should be finite and non-negative,got {}",
+                    exponent
+                );
+                // weight of the second account underflows with huge exponent
+                assert!(
+                    2f64.powf(exponent).recip() > 0.0,
+                    "zipfian exponent {} is too big to sample two accounts",
+                    exponent
+                );
+            }
+            Self::Hotspot { traffic, keys } => {
+                assert!(
+                    (0.0..=1.0).contains(&traffic) && (0.0..=1.0).contains(&keys),
+                    "hotspot traffic and keys should be in 0..=1,got {} and {}",
+                    traffic,
+                    keys
+                );
+                let hot_num = Self::hot_num(keys, accounts_num);
+                assert!(
+                    (traffic < 1.0 || hot_num >= 2)
+                        && (traffic > 0.0 || accounts_num - hot_num >= 2),
+                    "hotspot with traffic {} and keys {} samples a single account out of {}",
+                    traffic,
+                    keys,
+                    accounts_num
+                );
+            }
+        }
+    }
+}
+/// sampler of accounts following distribution
+enum AccountSampler {
+    Uniform(Uniform<usize>),
+    Weighted(WeightedIndex<f64>),
+    Hotspot {
+        traffic: f64,
+        hot: Uniform<usize>,
+        cold: Uniform<usize>,
+    },
+}
+impl AccountSampler {
+    fn new(distribution: AccountDistribution, accounts_num: usize) -> Self {
+        match distribution {
+            AccountDistribution::Uniform => Self::Uniform(Uniform::from(0..accounts_num)),
+            AccountDistribution::Zipfian { exponent } => Self::Weighted(
+                WeightedIndex::new(
+                    (0..accounts_num).map(|i| 1.0 / ((i + 1) as f64).powf(exponent)),
+                )
+                .expect("exponent is checked by `with_distribution`"),
+            ),
+            AccountDistribution::Hotspot { traffic, keys } => {
+                let hot_num = AccountDistribution::hot_num(keys, accounts_num);
+                Self::Hotspot {
+                    traffic,
+                    hot: Uniform::from(0..hot_num),
+                    cold: Uniform::from(hot_num..accounts_num),
+                }
+            }
+            // senders of sink
+            AccountDistribution::Sink => Self::Uniform(Uniform::from(1..accounts_num)),
+        }
+    }
+    fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        match self {
+            Self::Uniform(uniform) => uniform.sample(rng),
+            Self::Weighted(weighted) => weighted.sample(rng),
+            Self::Hotspot { traffic, hot, cold } => {
+                if rng.gen_bool(*traffic) {
+                    hot.sample(rng)
+                } else {
+                    cold.sample(rng)
+                }
+            }
+        }
+    }
+}
+/// seedable generator of transfer txns and genesis ledger,same seed generates same block
+#[derive(Clone, Debug)]
+pub struct TransferGenerator {
+    accounts_num: usize,
+    init_balance: usize,
+    min_txn_money: usize,
+    max_txn_money: usize,
+    distribution: AccountDistribution,
+    seed: u64,
+}
+impl TransferGenerator {
+    /// uniform generator with random seed,`accounts_num` should be at least 2
+    pub fn new(accounts_num: usize, init_balance: usize) -> Self {
+        assert!(accounts_num >= 2, "at least 2 accounts to transfer between");
+        Self {
+            accounts_num,
+            init_balance,
+            min_txn_money: 1,
+            max_txn_money: 1_000,
+            distribution: AccountDistribution::Uniform,
+            seed: rand::random(),
+        }
+    }
+    /// money of each txn is in `min_txn_money..=max_txn_money`
+    pub fn with_money(mut self, min_txn_money: usize, max_txn_money: usize) -> Self {
+        assert!(
+            min_txn_money <= max_txn_money,
+            "min txn money {} is bigger than max {}",
+            min_txn_money,
+            max_txn_money
+        );
+        self.min_txn_money = min_txn_money;
+        self.max_txn_money = max_txn_money;
+        self
+    }
+    /// set distribution of accessed accounts,
+    /// panic if it can't sample two different accounts,e.g. all traffic on a single hot account
+    pub fn with_distribution(mut self, distribution: AccountDistribution) -> Self {
+        distribution.check(self.accounts_num);
+        self.distribution = distribution;
+        self
+    }
+    /// set seed,e.g. the one reported by a failed test
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+    /// seed of generator
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// generate `txns_num` txns and genesis ledger
+    pub fn generate(&self, txns_num: usize) -> (Vec<TransferTransaction>, Ledger) {
+        let ledger = generate_ledger(self.accounts_num, self.init_balance);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let accounts = AccountSampler::new(self.distribution, self.accounts_num);
+        let money_distribution = Uniform::from(self.min_txn_money..=self.max_txn_money);
+        let mut txns = Vec::with_capacity(txns_num);
+        while txns.len() < txns_num {
+            let from = accounts.sample(&mut rng);
+            let to = match self.distribution {
+                AccountDistribution::Sink => 0,
+                _ => accounts.sample(&mut rng),
+            };
+            let money = money_distribution.sample(&mut rng);
+            if from != to {
+                txns.push(TransferTransaction { from, to, money });
+            }
+        }
+        (txns, ledger)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::{AccountDistribution, TransferGenerator};
+
+    #[test]
+    fn smallest_distributions() {
+        let distributions = [
+            AccountDistribution::Uniform,
+            AccountDistribution::Zipfian { exponent: 2.0 },
+            AccountDistribution::Hotspot {
+                traffic: 0.5,
+                keys: 1.0,
+            },
+            AccountDistribution::Sink,
+        ];
+        for distribution in distributions {
+            let (txns, _) = TransferGenerator::new(2, 1_000)
+                .with_distribution(distribution)
+                .generate(10);
+            assert!(txns.iter().all(|txn| txn.from != txn.to && txn.to < 2));
+        }
+    }
+    #[test]
+    #[should_panic(expected = "samples a single account")]
+    fn single_hot_account() {
+        TransferGenerator::new(100, 1_000).with_distribution(AccountDistribution::Hotspot {
+            traffic: 1.0,
+            keys: 0.01,
+        });
+    }
+    #[test]
+    #[should_panic(expected = "should be in 0..=1")]
+    fn invalid_traffic() {
+        TransferGenerator::new(100, 1_000).with_distribution(AccountDistribution::Hotspot {
+            traffic: 1.5,
+            keys: 0.1,
+        });
+    }
+    #[test]
+    #[should_panic(expected = "finite and non-negative")]
+    fn invalid_exponent() {
+        TransferGenerator::new(100, 1_000)
+            .with_distribution(AccountDistribution::Zipfian { exponent: f64::NAN });
+    }
+}
@@ -6,16 +6,27 @@ use std::{
 
 use anyhow::anyhow;
 use either::Either;
-use rand::{distributions::Uniform, prelude::Distribution};
 
 mod aptos_impl;
 pub use aptos_impl::aptos_parallel_execute;
+mod generator;
+pub use generator::{AccountDistribution, TransferGenerator};
 mod my_impl;
-pub use my_impl::my_parallel_execute;
+pub use my_impl::{
+    my_parallel_execute, my_parallel_execute_adaptive, my_parallel_execute_contention,
+    my_parallel_execute_on, my_parallel_execute_ordered, my_parallel_execute_partitioned,
+    my_parallel_execute_verified, my_parallel_execute_with_commitment,
+    my_parallel_execute_with_context, my_parallel_execute_with_outputs,
+    my_parallel_execute_with_store, my_worker_serve,
+};
+/// transaction families beyond transfers
+pub mod workload;
 
 use super::BenchmarkInfo;
+use crate::{reorder::KeySet, storage::BaseView};
 ///
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TransferTransaction {
     /// transfer money from
     pub from: usize,
@@ -24,11 +35,22 @@ pub struct TransferTransaction {
     /// transfer money amount
     pub money: usize,
 }
+impl TransferTransaction {
+    /// declared key set,used by reorder and partition
+    pub fn key_set(&self) -> KeySet<usize> {
+        KeySet {
+            reads: vec![self.from, self.to],
+            writes: vec![self.from, self.to],
+        }
+    }
+}
 ///
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TransferTransactionOutput(Vec<(usize, usize)>);
 ///
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ledger(HashMap<usize, usize>);
 impl Deref for Ledger {
     type Target = HashMap<usize, usize>;
@@ -42,6 +64,11 @@ impl DerefMut for Ledger {
         &mut self.0
     }
 }
+impl BaseView<usize, usize> for Ledger {
+    fn read(&self, key: &usize) -> Option<usize> {
+        self.0.get(key).copied()
+    }
+}
 impl Ledger {
     ///
     pub fn apply(
@@ -70,31 +97,29 @@ impl Ledger {
         self
     }
 }
-/// generate random txns and genesis ledger with the given parameters
+/// generate genesis ledger,in which each account has `init_balance`
+pub fn generate_ledger(accounts_num: usize, init_balance: usize) -> Ledger {
+    let mut ledger = Ledger::default();
+    (0..accounts_num).into_iter().for_each(|account| {
+        let _ = ledger.insert(account, init_balance);
+    });
+    ledger
+}
+/// generate random txns and genesis ledger with the given parameters,accounts are accessed uniformly.
+///
+/// same seed generates same block,so tests should report the seed to reproduce failures.
 pub fn generate_txns_and_ledger(
     accounts_num: usize,
     init_balance: usize,
     txns_num: usize,
     min_txn_money: usize,
     max_txn_money: usize,
+    seed: u64,
 ) -> (Vec<TransferTransaction>, Ledger) {
-    let mut ledger = Ledger::default();
-    (0..accounts_num).into_iter().for_each(|account| {
-        let _ = ledger.insert(account, init_balance);
-    });
-    let mut txns = Vec::new();
-    let mut rng = rand::thread_rng();
-    let account_distribution = Uniform::from(0..accounts_num);
-    let money_distribution = Uniform::from(min_txn_money..=max_txn_money);
-    while txns.len() < txns_num {
-        let from = account_distribution.sample(&mut rng);
-        let to = account_distribution.sample(&mut rng);
-        let money = money_distribution.sample(&mut rng);
-        if from != to {
-            txns.push(TransferTransaction { from, to, money });
-        }
-    }
-    (txns, ledger)
+    TransferGenerator::new(accounts_num, init_balance)
+        .with_money(min_txn_money, max_txn_money)
+        .with_seed(seed)
+        .generate(txns_num)
 }
 struct SequentialVM(Ledger);
 impl SequentialVM {
@@ -110,9 +135,6 @@ impl SequentialVM {
         &self,
         txn: &TransferTransaction,
     ) -> anyhow::Result<TransferTransactionOutput> {
-        #[cfg(feature = "benchmark")]
-        std::thread::sleep(std::time::Duration::from_micros(100));
-
         let read = |k| match self.0.get(k) {
             Some(v) => Ok(*v),
             None => Err(anyhow!("value not found")),
@@ -127,6 +149,9 @@ impl SequentialVM {
         } else {
             vec![]
         };
+        // `to` is only read by successful transfer
+        #[cfg(feature = "benchmark")]
+        crate::test_utils::cost::charge(1 + output.len() / 2, output.len());
         Ok(TransferTransactionOutput(output))
     }
 }
@@ -151,28 +176,398 @@ pub fn sequential_execute(
             total_time: total.elapsed(),
             execute_time: None,
             collect_time: None,
+            num_aborts: None,
         },
     )
 }
 #[cfg(test)]
 mod tests {
     use super::{aptos_impl::aptos_parallel_execute, my_impl::my_parallel_execute, *};
+    use crate::{
+        test_utils::{
+            differential::{
+                differential_test, differential_test_with, snapshot_of_write_sets,
+                DifferentialConfig, Snapshot,
+            },
+            WATCHDOG_INTERVAL,
+        },
+        ParallelExecutor, ParallelExecutorError,
+    };
     #[test]
     fn test_aptos_parallel_execute() {
-        let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000);
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000, seed);
         let (s_output, _) = sequential_execute(&txns, &ledger);
         let (ap_output, _) = aptos_parallel_execute(&txns, &ledger, num_cpus::get());
-        assert_eq!(s_output, ap_output);
+        assert_eq!(s_output, ap_output, "seed {}", seed);
     }
     #[test]
     fn test_my_parallel_execute() {
-        let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000);
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000, seed);
         let (s_output, _) = sequential_execute(&txns, &ledger);
         let (mp_output, _) = my_parallel_execute(&txns, &ledger, num_cpus::get());
         let cloned = ledger.clone();
         assert_eq!(
             ledger.apply(Either::Left(s_output)),
-            cloned.apply(Either::Right(mp_output))
+            cloned.apply(Either::Right(mp_output)),
+            "seed {}",
+            seed
         )
     }
+    #[test]
+    fn test_my_parallel_execute_skewed() {
+        let distributions = [
+            AccountDistribution::Zipfian { exponent: 1.0 },
+            AccountDistribution::Hotspot {
+                traffic: 0.9,
+                keys: 0.1,
+            },
+            AccountDistribution::Sink,
+        ];
+        for distribution in distributions {
+            let generator = TransferGenerator::new(100, 1_000_000).with_distribution(distribution);
+            let (txns, ledger) = generator.generate(1_000);
+            // same seed generates same block
+            assert_eq!(txns, generator.generate(1_000).0);
+            let (s_output, _) = sequential_execute(&txns, &ledger);
+            let (mp_output, _) = my_parallel_execute(&txns, &ledger, num_cpus::get());
+            let cloned = ledger.clone();
+            assert_eq!(
+                ledger.apply(Either::Left(s_output)),
+                cloned.apply(Either::Right(mp_output)),
+                "{:?} with seed {}",
+                distribution,
+                generator.seed()
+            )
+        }
+    }
+    #[test]
+    fn test_workloads() {
+        use super::workload::*;
+        fn check<W: Workload>(workload: W) {
+            let seed = rand::random();
+            let (_, state) = workload.generate(0, seed);
+            let mut blocks = 0;
+            differential_test::<WorkloadVM<W>, _, _, _>(
+                &state,
+                &state,
+                |txns, state| {
+                    snapshot_of_write_sets(sequential_execute_workload::<W>(txns, state).0)
+                },
+                |txns_num| {
+                    blocks += 1;
+                    workload.generate(txns_num, seed.wrapping_add(blocks)).0
+                },
+                &DifferentialConfig::default(),
+            )
+            .unwrap_or_else(|e| panic!("{} with seed {}: {}", workload.name(), seed, e));
+        }
+        check(AmmWorkload {
+            pools: 3,
+            init_reserve: 1_000_000,
+        });
+        check(NftWorkload { owners: 100 });
+        check(NonceWorkload {
+            accounts: 5,
+            stale_ratio: 0.1,
+        });
+        check(OracleWorkload {
+            feeds: 10,
+            accounts: 100,
+            feeds_per_lookup: 3,
+            update_ratio: 0.05,
+        });
+    }
+    #[test]
+    fn test_workload_mint_creates_token() {
+        use super::workload::*;
+        use crate::core::WriteOp;
+        let workload = NftWorkload { owners: 10 };
+        let (txns, state) = workload.generate(100, 0);
+        let executor = ParallelExecutor::<_, WorkloadVM<NftWorkload>>::new(num_cpus::get())
+            .with_watchdog(WATCHDOG_INTERVAL);
+        let block = executor.execute(&txns, &state).unwrap();
+        // mint bumps the existing counter and creates the owner key of its token
+        for (id, changes) in block.changes(|k| state.read(k)).into_iter().enumerate() {
+            let changes: Vec<_> = changes
+                .into_iter()
+                .map(|change| (change.key, change.before, change.after))
+                .collect();
+            assert_eq!(
+                changes,
+                vec![
+                    (0, Some(id), WriteOp::Modify(id + 1)),
+                    (1 + id, None, WriteOp::Create(txns[id].owner))
+                ]
+            );
+        }
+    }
+    #[test]
+    fn test_my_differential() {
+        let generator = TransferGenerator::new(5, 1_000_000);
+        let ledger = generate_ledger(5, 1_000_000);
+        differential_test::<my_impl::ParallelVM, _, _, _>(
+            &ledger,
+            &ledger,
+            |txns, ledger| {
+                snapshot_of_write_sets(sequential_execute(txns, ledger).0.into_iter().map(|o| o.0))
+            },
+            |txns_num| generator.generate(txns_num).0,
+            &DifferentialConfig::default(),
+        )
+        .unwrap_or_else(|e| panic!("seed {}: {}", generator.seed(), e));
+    }
+    /// differential test of transfers among `accounts_num` accounts,executed in parallel by `execute`
+    fn my_differential_with<E>(accounts_num: usize, config: &DifferentialConfig, mut execute: E)
+    where
+        E: FnMut(
+            &[TransferTransaction],
+            &Ledger,
+            usize,
+        ) -> Result<Snapshot<TransferTransaction>, ParallelExecutorError>,
+    {
+        let generator = TransferGenerator::new(accounts_num, 1_000_000);
+        let ledger = generate_ledger(accounts_num, 1_000_000);
+        differential_test_with(
+            &ledger,
+            |txns, ledger| {
+                snapshot_of_write_sets(sequential_execute(txns, ledger).0.into_iter().map(|o| o.0))
+            },
+            |txns_num| generator.generate(txns_num).0,
+            |txns, concurrency_level| execute(txns, &ledger, concurrency_level),
+            config,
+        )
+        .unwrap_or_else(|e| panic!("seed {}: {}", generator.seed(), e));
+    }
+    #[test]
+    fn test_my_parallel_execute_verified() {
+        my_differential_with(5, &DifferentialConfig::default(), |txns, ledger, level| {
+            ParallelExecutor::<_, my_impl::ParallelVM>::new(level)
+                .with_watchdog(WATCHDOG_INTERVAL)
+                .verify_determinism(txns, ledger, 3)
+        });
+    }
+    #[test]
+    fn test_my_parallel_execute_adaptive() {
+        let max = num_cpus::get();
+        let mut adaptive = crate::AdaptiveConcurrency::new(max);
+        let config = DifferentialConfig {
+            concurrency_levels: vec![max],
+            ..DifferentialConfig::default()
+        };
+        // consecutive blocks of high and low contention
+        for accounts_num in [3, 1_000] {
+            my_differential_with(accounts_num, &config, |txns, ledger, level| {
+                let snapshot = ParallelExecutor::<_, my_impl::ParallelVM>::new(level)
+                    .with_watchdog(WATCHDOG_INTERVAL)
+                    .execute_transactions_adaptive(&mut adaptive, txns, ledger)?;
+                // with enough workers,nearly every transaction of 3 accounts conflicts with its neighbours
+                if accounts_num == 3 && txns.len() == config.txns_num && max >= 4 {
+                    let settled = adaptive.settled().expect("block is observed");
+                    assert!(settled < max, "throttle settled at {} of {}", settled, max);
+                }
+                Ok(snapshot)
+            });
+        }
+        assert!(adaptive.best().is_some());
+    }
+    #[test]
+    fn test_my_parallel_execute_with_dense_store() {
+        my_differential_with(5, &DifferentialConfig::default(), |txns, ledger, level| {
+            ParallelExecutor::<_, my_impl::ParallelVM>::new(level)
+                .with_store(crate::DenseMVMapConfig { capacity: 5 })
+                .with_watchdog(WATCHDOG_INTERVAL)
+                .execute_transactions(txns, ledger)
+        });
+    }
+    #[test]
+    fn test_my_parallel_execute_contention() {
+        // few accounts,so that validations abort
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(3, 1_000_000, 1_000, 1, 1_000, seed);
+        for _ in 0..3 {
+            let (_, report) = ParallelExecutor::<_, my_impl::ParallelVM>::new(num_cpus::get())
+                .with_watchdog(WATCHDOG_INTERVAL)
+                .execute_transactions_benchmark(&txns, &ledger)
+                .unwrap_or_else(|e| panic!("seed {}: {}", seed, e));
+            // every aborted incarnation is attributed to the key it read
+            assert_eq!(
+                report.contention.num_aborts(),
+                report.num_aborts,
+                "seed {}",
+                seed
+            );
+        }
+    }
+    #[test]
+    fn test_my_parallel_execute_reordered() {
+        use crate::reorder::{reorder, ReorderStrategy};
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000, seed);
+        for strategy in [ReorderStrategy::Spread, ReorderStrategy::Cluster] {
+            let (txns, _) = reorder(txns.clone(), TransferTransaction::key_set, strategy);
+            let (s_output, _) = sequential_execute(&txns, &ledger);
+            let (mp_output, _) = my_parallel_execute(&txns, &ledger, num_cpus::get());
+            let cloned = ledger.clone();
+            assert_eq!(
+                ledger.clone().apply(Either::Left(s_output)),
+                cloned.apply(Either::Right(mp_output)),
+                "{:?} with seed {}",
+                strategy,
+                seed
+            )
+        }
+    }
+    #[test]
+    fn test_my_parallel_execute_with_context() {
+        let mut context = crate::ExecutorContext::new();
+        // different block sizes and accounts to check that no state leaks between blocks
+        for (accounts_num, txns_num) in [(5, 1_000), (100, 100), (10, 500)] {
+            let seed = rand::random();
+            let (txns, ledger) =
+                generate_txns_and_ledger(accounts_num, 1_000_000, txns_num, 1, 1_000, seed);
+            let (s_output, _) = sequential_execute(&txns, &ledger);
+            let (mp_output, _) =
+                my_parallel_execute_with_context(&txns, &ledger, num_cpus::get(), &mut context);
+            let cloned = ledger.clone();
+            assert_eq!(
+                ledger.apply(Either::Left(s_output)),
+                cloned.apply(Either::Right(mp_output)),
+                "seed {}",
+                seed
+            )
+        }
+    }
+    #[test]
+    fn test_my_parallel_execute_partitioned() {
+        // many accounts and few txns,so there are several partitions
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(1_000, 1_000_000, 200, 1, 1_000, seed);
+        let (s_output, _) = sequential_execute(&txns, &ledger);
+        let (mp_output, _) = my_parallel_execute_partitioned(
+            &txns,
+            &ledger,
+            num_cpus::get(),
+            TransferTransaction::key_set,
+        );
+        let cloned = ledger.clone();
+        assert_eq!(
+            ledger.apply(Either::Left(s_output)),
+            cloned.apply(Either::Right(mp_output)),
+            "seed {}",
+            seed
+        )
+    }
+    #[test]
+    fn test_my_parallel_execute_partitioned_mispredicted() {
+        // only `from` is predicted,partitions conflict at runtime and execution falls back
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000, seed);
+        let (s_output, _) = sequential_execute(&txns, &ledger);
+        let (mp_output, _) =
+            my_parallel_execute_partitioned(&txns, &ledger, num_cpus::get(), |txn| KeySet {
+                reads: vec![txn.from],
+                writes: vec![txn.from],
+            });
+        let cloned = ledger.clone();
+        assert_eq!(
+            ledger.apply(Either::Left(s_output)),
+            cloned.apply(Either::Right(mp_output)),
+            "seed {}",
+            seed
+        )
+    }
+    #[test]
+    fn test_my_parallel_execute_ordered() {
+        use crate::SnapshotOrder;
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(100, 1_000_000, 1_000, 1, 1_000, seed);
+        let (s_output, _) = sequential_execute(&txns, &ledger);
+        // keys in order of their first write,with final values
+        let final_ledger = ledger.clone().apply(Either::Left(s_output.clone()));
+        let mut expected: Vec<(usize, Option<usize>)> = Vec::new();
+        for (key, _) in s_output.iter().flat_map(|output| output.0.iter()) {
+            if !expected.iter().any(|(k, _)| k == key) {
+                expected.push((*key, final_ledger.get(key).copied()));
+            }
+        }
+        let (mp_output, _) = my_parallel_execute_ordered(
+            &txns,
+            &ledger,
+            num_cpus::get(),
+            SnapshotOrder::FirstWriter,
+        );
+        assert_eq!(mp_output, expected, "seed {}", seed);
+        expected.sort_unstable();
+        let (mp_output, _) =
+            my_parallel_execute_ordered(&txns, &ledger, num_cpus::get(), SnapshotOrder::sorted());
+        assert_eq!(mp_output, expected, "seed {}", seed);
+    }
+    #[test]
+    fn test_my_parallel_execute_with_commitment() {
+        use crate::commitment::{accumulate, merkle_root, Sha256Hasher};
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000, seed);
+        let (s_output, _) = sequential_execute(&txns, &ledger);
+        let (mp_output, commitment) =
+            my_parallel_execute_with_commitment(&txns, &ledger, num_cpus::get());
+        // commitment computed from sequential execution
+        let write_sets: Vec<Vec<(usize, Option<usize>)>> = s_output
+            .iter()
+            .map(|output| output.0.iter().map(|(k, v)| (*k, Some(*v))).collect())
+            .collect();
+        assert_eq!(
+            commitment.accumulator,
+            accumulate(&Sha256Hasher, &write_sets),
+            "seed {}",
+            seed
+        );
+        let final_ledger = ledger.clone().apply(Either::Left(s_output));
+        let mut s_snapshot: Vec<_> = write_sets
+            .iter()
+            .flatten()
+            .map(|(k, _)| (*k, final_ledger.get(k).copied()))
+            .collect();
+        s_snapshot.sort_unstable();
+        s_snapshot.dedup();
+        assert_eq!(
+            commitment.state_root,
+            merkle_root(&Sha256Hasher, &s_snapshot),
+            "seed {}",
+            seed
+        );
+        assert_eq!(
+            commitment.state_root,
+            merkle_root(&Sha256Hasher, &mp_output),
+            "seed {}",
+            seed
+        );
+    }
+    #[test]
+    fn test_my_parallel_execute_on_file_storage() {
+        use crate::storage::{FileStorage, Storage};
+        let dir = std::env::temp_dir().join(format!("block_stm_simulated_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut storage = FileStorage::open(&dir).unwrap();
+        let mut ledger = generate_ledger(5, 1_000_000);
+        let genesis: Vec<_> = ledger.iter().map(|(k, v)| (*k, Some(*v))).collect();
+        storage.commit(&genesis).unwrap();
+        let seed: u64 = rand::random();
+        for block in 0..3 {
+            let (txns, _) =
+                generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000, seed.wrapping_add(block));
+            let (s_output, _) = sequential_execute(&txns, &ledger);
+            ledger = ledger.apply(Either::Left(s_output));
+            let (mp_output, _) = my_parallel_execute_on(&txns, &storage, num_cpus::get());
+            storage.commit(&mp_output).unwrap();
+        }
+        drop(storage);
+        let storage = FileStorage::<usize, usize>::open(&dir).unwrap();
+        assert_eq!(storage.version(), 4);
+        for (k, v) in ledger.iter() {
+            assert_eq!(storage.read(k), Some(*v), "seed {}", seed);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,10 +1,15 @@
-use std::time::Instant;
+use std::{io, time::Instant};
 
 use crate::{
-    core::{Transaction, TransactionOutput, ValueBytes, VM},
+    commitment::{Commitment, Sha256Hasher},
+    core::{Transaction, TransactionOutput, ValueBytes, WireBytes, WriteOp, VM},
+    multiprocess,
     mvmemory::ReadResult,
+    reorder::KeySet,
+    storage::BaseView,
     test_utils::BenchmarkInfo,
-    ParallelExecutor,
+    AdaptiveConcurrency, ContentionReport, ExecutorContext, MVMapConfig, MVStoreConfig,
+    ParallelExecutor, ParallelExecutorError, SnapshotOrder,
 };
 
 use super::{Ledger, TransferTransaction, TransferTransactionOutput};
@@ -15,8 +20,8 @@ impl Transaction for TransferTransaction {
     type Value = usize;
 }
 impl ValueBytes for <TransferTransaction as Transaction>::Value {
-    fn serialize(&self) -> Option<Vec<u8>> {
-        Some(self.to_ne_bytes().to_vec())
+    fn serialize(&self) -> Vec<u8> {
+        self.to_ne_bytes().to_vec()
     }
 
     fn deserialize(bytes: &[u8]) -> Self {
@@ -24,6 +29,24 @@ impl ValueBytes for <TransferTransaction as Transaction>::Value {
         usize::from_ne_bytes(bytes)
     }
 }
+impl WireBytes for TransferTransaction {
+    fn to_bytes(&self) -> Vec<u8> {
+        [self.from, self.to, self.money]
+            .iter()
+            .flat_map(|v| v.to_bytes())
+            .collect()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut fields = bytes.chunks(8).map(usize::from_bytes);
+        let mut next = || fields.next().expect("invalid transaction bytes");
+        Self {
+            from: next(),
+            to: next(),
+            money: next(),
+        }
+    }
+}
 impl TransactionOutput for TransferTransactionOutput {
     type T = TransferTransaction;
 
@@ -31,20 +54,24 @@ impl TransactionOutput for TransferTransactionOutput {
         &self,
     ) -> Vec<(
         <Self::T as Transaction>::Key,
-        <Self::T as Transaction>::Value,
+        WriteOp<<Self::T as Transaction>::Value>,
     )> {
-        self.0.clone()
+        self.0
+            .iter()
+            .map(|(k, v)| (*k, WriteOp::Modify(*v)))
+            .collect()
     }
 }
-struct ParallelVM<'a>(&'a Ledger);
-impl<'a> VM for ParallelVM<'a> {
+/// vm reading base state from `S`
+pub(super) struct ParallelVM<'a, S = Ledger>(&'a S);
+impl<'a, S: BaseView<usize, usize>> VM for ParallelVM<'a, S> {
     type T = TransferTransaction;
 
     type Output = TransferTransactionOutput;
 
     type Error = ();
 
-    type Parameter = &'a Ledger;
+    type Parameter = &'a S;
 
     fn new(argument: Self::Parameter) -> Self {
         Self(argument)
@@ -58,12 +85,10 @@ impl<'a> VM for ParallelVM<'a> {
             <Self::T as Transaction>::Value,
         >,
     ) -> Result<Self::Output, Self::Error> {
-        #[cfg(feature = "benchmark")]
-        std::thread::sleep(std::time::Duration::from_micros(100));
-
         let read = |k| match view.read(k) {
             ReadResult::Value(v) => Ok(*v),
-            ReadResult::NotFound => Ok(*self.0.get(k).unwrap()),
+            ReadResult::Deleted => unreachable!("accounts are never deleted"),
+            ReadResult::NotFound => Ok(self.0.read(k).unwrap()),
         };
         let from_balance = read(&txn.from)?;
         let output = if from_balance >= txn.money {
@@ -75,24 +100,225 @@ impl<'a> VM for ParallelVM<'a> {
         } else {
             vec![]
         };
+        // `to` is only read by successful transfer
+        #[cfg(feature = "benchmark")]
+        crate::test_utils::cost::charge(1 + output.len() / 2, output.len());
         Ok(TransferTransactionOutput(output))
     }
 }
+/// execute txns by `execute` on given executor (with watchdog unless benchmarking),panic on execution error,
+/// benchmark info only has total time
+fn my_parallel_execute_by<V, C, R, F>(
+    pe: ParallelExecutor<TransferTransaction, V, C>,
+    execute: F,
+) -> (R, BenchmarkInfo)
+where
+    V: VM<T = TransferTransaction>,
+    C: MVStoreConfig<usize, usize>,
+    F: FnOnce(&ParallelExecutor<TransferTransaction, V, C>) -> Result<R, ParallelExecutorError>,
+{
+    let total = Instant::now();
+    #[cfg(not(feature = "benchmark"))]
+    let pe = pe.with_watchdog(crate::test_utils::WATCHDOG_INTERVAL);
+    let output = execute(&pe).expect("parallel execute error");
+    (
+        output,
+        BenchmarkInfo {
+            total_time: total.elapsed(),
+            ..Default::default()
+        },
+    )
+}
+/// execute txns on given executor,benchmark info is filled by execution report
+fn my_parallel_execute_benchmark<C>(
+    pe: ParallelExecutor<TransferTransaction, ParallelVM, C>,
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo)
+where
+    C: MVStoreConfig<usize, usize>,
+{
+    let ((output, report), info) =
+        my_parallel_execute_by(pe, |pe| pe.execute_transactions_benchmark(txns, ledger));
+    (
+        output,
+        BenchmarkInfo {
+            execute_time: Some(report.execute_time),
+            collect_time: Some(report.collect_time),
+            num_aborts: Some(report.num_aborts),
+            ..info
+        },
+    )
+}
 /// parallel execute txns
 pub fn my_parallel_execute(
     txns: &Vec<TransferTransaction>,
     ledger: &Ledger,
     concurrency_level: usize,
 ) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo) {
-    let total = Instant::now();
+    my_parallel_execute_with_store(txns, ledger, concurrency_level, MVMapConfig::default())
+}
+/// parallel execute txns with mvstore created by given config
+pub fn my_parallel_execute_with_store<C>(
+    txns: &Vec<TransferTransaction>,
+    ledger: &Ledger,
+    concurrency_level: usize,
+    store_config: C,
+) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo)
+where
+    C: MVStoreConfig<usize, usize>,
+{
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(concurrency_level)
+        .with_store(store_config);
+    my_parallel_execute_benchmark(pe, txns, ledger)
+}
+/// parallel execute txns,also return per-transaction outputs,which are rebuilt from write sets of executed block,
+/// rebuilding them is counted by total time only
+#[allow(clippy::type_complexity)]
+pub fn my_parallel_execute_with_outputs(
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+    concurrency_level: usize,
+) -> (
+    Vec<TransferTransactionOutput>,
+    Vec<(usize, Option<usize>)>,
+    BenchmarkInfo,
+) {
     let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(concurrency_level);
-    let (output, execute, collect) = pe.execute_transactions_benchmark(txns, ledger);
+    let ((outputs, output, execute_time, collect_time, num_aborts), info) =
+        my_parallel_execute_by(pe, |pe| {
+            let execute = Instant::now();
+            let block = pe.execute(txns, ledger)?;
+            let execute_time = execute.elapsed();
+            let outputs = block
+                .changes(|k| ledger.read(k))
+                .into_iter()
+                .map(|changes| {
+                    TransferTransactionOutput(
+                        changes
+                            .into_iter()
+                            .map(|change| {
+                                (
+                                    change.key,
+                                    change
+                                        .after
+                                        .into_value()
+                                        .expect("accounts are never deleted"),
+                                )
+                            })
+                            .collect(),
+                    )
+                })
+                .collect();
+            let num_aborts = block.num_aborts();
+            let collect = Instant::now();
+            let output = block.collect();
+            Ok((outputs, output, execute_time, collect.elapsed(), num_aborts))
+        });
     (
+        outputs,
         output,
         BenchmarkInfo {
-            total_time: total.elapsed(),
-            execute_time: Some(execute),
-            collect_time: Some(collect),
+            execute_time: Some(execute_time),
+            collect_time: Some(collect_time),
+            num_aborts: Some(num_aborts),
+            ..info
         },
     )
 }
+/// parallel execute txns with adaptive concurrency learned from previous blocks
+pub fn my_parallel_execute_adaptive(
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+    adaptive: &mut AdaptiveConcurrency,
+) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo) {
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(num_cpus::get());
+    my_parallel_execute_by(pe, |pe| {
+        pe.execute_transactions_adaptive(adaptive, txns, ledger)
+    })
+}
+/// parallel execute txns,return keys and writers ranked by aborts they caused
+pub fn my_parallel_execute_contention(
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+    concurrency_level: usize,
+) -> ContentionReport<usize> {
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(concurrency_level);
+    let ((_, report), _) =
+        my_parallel_execute_by(pe, |pe| pe.execute_transactions_benchmark(txns, ledger));
+    report.contention
+}
+/// parallel execute txns,keys of output are in given order
+pub fn my_parallel_execute_ordered(
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+    concurrency_level: usize,
+    snapshot_order: SnapshotOrder<usize>,
+) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo) {
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(concurrency_level)
+        .with_snapshot_order(snapshot_order);
+    my_parallel_execute_benchmark(pe, txns, ledger)
+}
+/// parallel execute txns,then commit to output with `Sha256Hasher`
+#[allow(clippy::type_complexity)]
+pub fn my_parallel_execute_with_commitment(
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+    concurrency_level: usize,
+) -> (Vec<(usize, Option<usize>)>, Commitment<[u8; 32]>) {
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(concurrency_level);
+    my_parallel_execute_by(pe, |pe| {
+        pe.execute_transactions_with_commitment(txns, ledger, &Sha256Hasher)
+    })
+    .0
+}
+/// parallel execute txns on given base state (e.g. `FileStorage`)
+pub fn my_parallel_execute_on<S: BaseView<usize, usize>>(
+    txns: &[TransferTransaction],
+    base_view: &S,
+    concurrency_level: usize,
+) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo) {
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM<S>>::new(concurrency_level);
+    my_parallel_execute_by(pe, |pe| pe.execute_transactions(txns, base_view))
+}
+/// parallel execute txns with reused execution context
+pub fn my_parallel_execute_with_context(
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+    concurrency_level: usize,
+    context: &mut ExecutorContext<usize, usize>,
+) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo) {
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(concurrency_level);
+    my_parallel_execute_by(pe, |pe| {
+        pe.execute_transactions_with_context(context, txns, ledger)
+    })
+}
+/// parallel execute txns partitioned by given key sets
+pub fn my_parallel_execute_partitioned<F>(
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+    concurrency_level: usize,
+    key_set: F,
+) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo)
+where
+    F: Fn(&TransferTransaction) -> KeySet<usize>,
+{
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(concurrency_level);
+    my_parallel_execute_by(pe, |pe| {
+        pe.execute_transactions_partitioned(txns, key_set, ledger)
+    })
+}
+/// parallel execute txns `runs` times at different concurrency levels,check that all runs commit the same outcome
+pub fn my_parallel_execute_verified(
+    txns: &[TransferTransaction],
+    ledger: &Ledger,
+    concurrency_level: usize,
+    runs: usize,
+) -> (Vec<(usize, Option<usize>)>, BenchmarkInfo) {
+    let pe = ParallelExecutor::<TransferTransaction, ParallelVM>::new(concurrency_level);
+    my_parallel_execute_by(pe, |pe| pe.verify_determinism(txns, ledger, runs))
+}
+/// serve coordinator of multi-process execution on `input` (usually stdin) and stdout,used by worker binary
+pub fn my_worker_serve<R: io::Read + Send>(ledger: &Ledger, input: R) -> io::Result<()> {
+    multiprocess::serve::<ParallelVM, _, _>(ledger, input, io::stdout())
+}
@@ -0,0 +1,112 @@
+use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
+
+use super::{State, Workload};
+use crate::core::{Transaction, ValueBytes};
+
+/// reserves of pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pool {
+    /// reserve of token x
+    pub reserve_x: u64,
+    /// reserve of token y
+    pub reserve_y: u64,
+}
+impl ValueBytes for Pool {
+    fn serialize(&self) -> Vec<u8> {
+        [self.reserve_x, self.reserve_y]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Self {
+        let (x, y) = bytes.split_at(8);
+        Self {
+            reserve_x: u64::from_le_bytes(x.try_into().unwrap()),
+            reserve_y: u64::from_le_bytes(y.try_into().unwrap()),
+        }
+    }
+}
+/// swap on pool,which reads and writes one pool key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwapTransaction {
+    /// pool id
+    pub pool: usize,
+    /// amount of input token
+    pub amount_in: u64,
+    /// swap x for y if true,otherwise y for x
+    pub x_to_y: bool,
+}
+impl Transaction for SwapTransaction {
+    type Key = usize;
+
+    type Value = Pool;
+}
+/// constant product amm,txns swap on pools chosen uniformly
+#[derive(Clone, Debug)]
+pub struct AmmWorkload {
+    /// number of pools,fewer is more contended
+    pub pools: usize,
+    /// genesis reserves of both tokens
+    pub init_reserve: u64,
+}
+impl Workload for AmmWorkload {
+    type T = SwapTransaction;
+
+    type Key = usize;
+
+    type Value = Pool;
+
+    fn name(&self) -> String {
+        format!("amm (pools={})", self.pools)
+    }
+
+    fn generate(&self, txns_num: usize, seed: u64) -> (Vec<SwapTransaction>, State<usize, Pool>) {
+        let mut state = State::default();
+        for pool in 0..self.pools {
+            state.insert(
+                pool,
+                Pool {
+                    reserve_x: self.init_reserve,
+                    reserve_y: self.init_reserve,
+                },
+            );
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let pool_distribution = Uniform::from(0..self.pools);
+        let amount_distribution = Uniform::from(1..=1_000);
+        let txns = (0..txns_num)
+            .map(|_| SwapTransaction {
+                pool: pool_distribution.sample(&mut rng),
+                amount_in: amount_distribution.sample(&mut rng),
+                x_to_y: rng.gen(),
+            })
+            .collect();
+        (txns, state)
+    }
+
+    fn execute(txn: &SwapTransaction, read: &dyn Fn(&usize) -> Option<Pool>) -> Vec<(usize, Pool)> {
+        let pool = read(&txn.pool).expect("pool not found");
+        let (reserve_in, reserve_out) = if txn.x_to_y {
+            (pool.reserve_x, pool.reserve_y)
+        } else {
+            (pool.reserve_y, pool.reserve_x)
+        };
+        // x * y = k
+        let amount_out = (reserve_out as u128 * txn.amount_in as u128
+            / (reserve_in as u128 + txn.amount_in as u128)) as u64;
+        let (reserve_in, reserve_out) = (reserve_in + txn.amount_in, reserve_out - amount_out);
+        let pool = if txn.x_to_y {
+            Pool {
+                reserve_x: reserve_in,
+                reserve_y: reserve_out,
+            }
+        } else {
+            Pool {
+                reserve_x: reserve_out,
+                reserve_y: reserve_in,
+            }
+        };
+        vec![(txn.pool, pool)]
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    time::Instant,
+};
+
+use crate::{
+    core::{Transaction, TransactionOutput, ValueBytes, WriteOp, VM},
+    mvmemory::{MVMemoryView, ReadResult},
+    storage::BaseView,
+    test_utils::BenchmarkInfo,
+    ParallelExecutor,
+};
+
+/// constant product swaps on pools
+mod amm;
+pub use amm::{AmmWorkload, Pool, SwapTransaction};
+/// mints bumping a global counter
+mod nft;
+pub use nft::{MintTransaction, NftWorkload};
+/// sequence checked account nonces
+mod nonce;
+pub use nonce::{NonceTransaction, NonceWorkload};
+/// read-mostly oracle lookups
+mod oracle;
+pub use oracle::{OracleTransaction, OracleWorkload};
+
+/// simulated transaction family,which has its own read/write shape.
+///
+/// `execute` is the state transition function of family,shared by sequential reference and parallel vm.
+pub trait Workload {
+    /// transaction of family
+    type T: Transaction<Key = Self::Key, Value = Self::Value> + Clone;
+    /// key of state
+    type Key: Eq + Hash + Clone + Send + Sync + Debug;
+    /// value of state
+    type Value: Clone + PartialEq + Send + Sync + ValueBytes + Debug;
+    /// name with parameters,used by benches
+    fn name(&self) -> String;
+    /// generate `txns_num` txns and genesis state,same seed generates same block
+    #[allow(clippy::type_complexity)]
+    fn generate(&self, txns_num: usize, seed: u64)
+        -> (Vec<Self::T>, State<Self::Key, Self::Value>);
+    /// execute txn with given reader of state,return its write set
+    fn execute(
+        txn: &Self::T,
+        read: &dyn Fn(&Self::Key) -> Option<Self::Value>,
+    ) -> Vec<(Self::Key, Self::Value)>;
+}
+/// state of workload
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State<K: Eq + Hash, V>(HashMap<K, V>);
+impl<K: Eq + Hash, V> Default for State<K, V> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+impl<K: Eq + Hash, V> Deref for State<K, V> {
+    type Target = HashMap<K, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl<K: Eq + Hash, V> DerefMut for State<K, V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+impl<K, V> BaseView<K, V> for State<K, V>
+where
+    K: Eq + Hash + Sync,
+    V: Clone + Sync,
+{
+    fn read(&self, key: &K) -> Option<V> {
+        self.0.get(key).cloned()
+    }
+}
+impl<K: Eq + Hash, V> State<K, V> {
+    /// apply write sets of sequential execution
+    pub fn apply_write_sets(mut self, write_sets: Vec<Vec<(K, V)>>) -> Self {
+        self.0.extend(write_sets.into_iter().flatten());
+        self
+    }
+    /// apply snapshot of parallel execution
+    pub fn apply_snapshot(mut self, snapshot: Vec<(K, Option<V>)>) -> Self {
+        for (k, v) in snapshot {
+            match v {
+                Some(v) => self.0.insert(k, v),
+                None => self.0.remove(&k),
+            };
+        }
+        self
+    }
+}
+/// write set of workload transaction,`WriteOp::Create` if key doesn't exist before transaction
+pub struct WorkloadOutput<W: Workload>(Vec<(W::Key, WriteOp<W::Value>)>);
+impl<W: Workload> TransactionOutput for WorkloadOutput<W> {
+    type T = W::T;
+
+    fn get_write_set(&self) -> Vec<(W::Key, WriteOp<W::Value>)> {
+        self.0.clone()
+    }
+}
+/// parallel vm of workload,reading base state from `State`
+pub(crate) struct WorkloadVM<'a, W: Workload>(&'a State<W::Key, W::Value>, PhantomData<fn() -> W>);
+impl<'a, W: Workload> VM for WorkloadVM<'a, W> {
+    type T = W::T;
+
+    type Output = WorkloadOutput<W>;
+
+    type Error = ();
+
+    type Parameter = &'a State<W::Key, W::Value>;
+
+    fn new(argument: Self::Parameter) -> Self {
+        Self(argument, PhantomData)
+    }
+
+    fn execute_transaction(
+        &self,
+        txn: &Self::T,
+        view: &MVMemoryView<W::Key, W::Value>,
+    ) -> Result<Self::Output, Self::Error> {
+        let state = |k: &W::Key| match view.read(k) {
+            ReadResult::Value(v) => Some((*v).clone()),
+            ReadResult::Deleted => None,
+            ReadResult::NotFound => self.0.read(k),
+        };
+        // whether keys read by transaction exist before it
+        let prior = RefCell::new(HashMap::new());
+        let reads = Cell::new(0);
+        let read = |k: &W::Key| {
+            reads.set(reads.get() + 1);
+            let v = state(k);
+            prior.borrow_mut().insert(k.clone(), v.is_some());
+            v
+        };
+        let write_set = W::execute(txn, &read);
+        let prior = prior.into_inner();
+        #[cfg(feature = "benchmark")]
+        crate::test_utils::cost::charge(reads.get(), write_set.len());
+        // key written without being read is read at the end,like `OpsTransaction::execute`
+        let write_set = write_set
+            .into_iter()
+            .map(|(k, v)| {
+                let existed = prior
+                    .get(&k)
+                    .copied()
+                    .unwrap_or_else(|| state(&k).is_some());
+                let op = if existed {
+                    WriteOp::Modify(v)
+                } else {
+                    WriteOp::Create(v)
+                };
+                (k, op)
+            })
+            .collect();
+        Ok(WorkloadOutput(write_set))
+    }
+}
+/// sequential execute txns of workload,return write set of each txn
+#[allow(clippy::type_complexity)]
+pub fn sequential_execute_workload<W: Workload>(
+    txns: &[W::T],
+    state: &State<W::Key, W::Value>,
+) -> (Vec<Vec<(W::Key, W::Value)>>, BenchmarkInfo) {
+    let total = Instant::now();
+    let mut state = state.clone();
+    let output = txns
+        .iter()
+        .map(|txn| {
+            let reads = Cell::new(0);
+            let write_set = W::execute(txn, &|k| {
+                reads.set(reads.get() + 1);
+                state.get(k).cloned()
+            });
+            #[cfg(feature = "benchmark")]
+            crate::test_utils::cost::charge(reads.get(), write_set.len());
+            state.extend(write_set.iter().cloned());
+            write_set
+        })
+        .collect();
+    (
+        output,
+        BenchmarkInfo {
+            total_time: total.elapsed(),
+            execute_time: None,
+            collect_time: None,
+            num_aborts: None,
+        },
+    )
+}
+/// parallel execute txns of workload
+#[allow(clippy::type_complexity)]
+pub fn parallel_execute_workload<W: Workload>(
+    txns: &[W::T],
+    state: &State<W::Key, W::Value>,
+    concurrency_level: usize,
+) -> (Vec<(W::Key, Option<W::Value>)>, BenchmarkInfo) {
+    let total = Instant::now();
+    let pe = ParallelExecutor::<W::T, WorkloadVM<W>>::new(concurrency_level);
+    #[cfg(not(feature = "benchmark"))]
+    let pe = pe.with_watchdog(crate::test_utils::WATCHDOG_INTERVAL);
+    let (output, report) = pe
+        .execute_transactions_benchmark(txns, state)
+        .expect("parallel execute error");
+    (
+        output,
+        BenchmarkInfo {
+            total_time: total.elapsed(),
+            execute_time: Some(report.execute_time),
+            collect_time: Some(report.collect_time),
+            num_aborts: Some(report.num_aborts),
+        },
+    )
+}
@@ -0,0 +1,56 @@
+use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng, SeedableRng};
+
+use super::{State, Workload};
+use crate::core::Transaction;
+
+/// key of global mint counter,key `1 + id` is owner of token `id`
+const COUNTER: usize = 0;
+/// mint a token,which bumps the global counter,so every pair of mints conflicts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MintTransaction {
+    /// owner of minted token
+    pub owner: usize,
+}
+impl Transaction for MintTransaction {
+    type Key = usize;
+
+    type Value = usize;
+}
+/// nft mints by owners chosen uniformly
+#[derive(Clone, Debug)]
+pub struct NftWorkload {
+    /// number of owners
+    pub owners: usize,
+}
+impl Workload for NftWorkload {
+    type T = MintTransaction;
+
+    type Key = usize;
+
+    type Value = usize;
+
+    fn name(&self) -> String {
+        format!("nft (owners={})", self.owners)
+    }
+
+    fn generate(&self, txns_num: usize, seed: u64) -> (Vec<MintTransaction>, State<usize, usize>) {
+        let mut state = State::default();
+        state.insert(COUNTER, 0);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let owner_distribution = Uniform::from(0..self.owners);
+        let txns = (0..txns_num)
+            .map(|_| MintTransaction {
+                owner: owner_distribution.sample(&mut rng),
+            })
+            .collect();
+        (txns, state)
+    }
+
+    fn execute(
+        txn: &MintTransaction,
+        read: &dyn Fn(&usize) -> Option<usize>,
+    ) -> Vec<(usize, usize)> {
+        let id = read(&COUNTER).expect("counter not found");
+        vec![(COUNTER, id + 1), (1 + id, txn.owner)]
+    }
+}
@@ -0,0 +1,72 @@
+use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
+
+use super::{State, Workload};
+use crate::core::Transaction;
+
+/// txn of sender with sequence number,which only succeeds if it equals nonce of sender
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonceTransaction {
+    /// sender account
+    pub sender: usize,
+    /// sequence number
+    pub nonce: usize,
+}
+impl Transaction for NonceTransaction {
+    type Key = usize;
+
+    type Value = usize;
+}
+/// account nonces with sequence checks,senders are chosen uniformly
+#[derive(Clone, Debug)]
+pub struct NonceWorkload {
+    /// number of accounts,fewer is more contended
+    pub accounts: usize,
+    /// fraction (0..=1) of txns with stale nonce,which fail sequence check
+    pub stale_ratio: f64,
+}
+impl Workload for NonceWorkload {
+    type T = NonceTransaction;
+
+    type Key = usize;
+
+    type Value = usize;
+
+    fn name(&self) -> String {
+        format!("nonce (accounts={})", self.accounts)
+    }
+
+    fn generate(&self, txns_num: usize, seed: u64) -> (Vec<NonceTransaction>, State<usize, usize>) {
+        let mut state = State::default();
+        for account in 0..self.accounts {
+            state.insert(account, 0);
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let sender_distribution = Uniform::from(0..self.accounts);
+        let mut expected = vec![0usize; self.accounts];
+        let txns = (0..txns_num)
+            .map(|_| {
+                let sender = sender_distribution.sample(&mut rng);
+                let nonce = if rng.gen_bool(self.stale_ratio) {
+                    expected[sender].saturating_sub(1)
+                } else {
+                    expected[sender] += 1;
+                    expected[sender] - 1
+                };
+                NonceTransaction { sender, nonce }
+            })
+            .collect();
+        (txns, state)
+    }
+
+    fn execute(
+        txn: &NonceTransaction,
+        read: &dyn Fn(&usize) -> Option<usize>,
+    ) -> Vec<(usize, usize)> {
+        let nonce = read(&txn.sender).expect("account not found");
+        if nonce == txn.nonce {
+            vec![(txn.sender, nonce + 1)]
+        } else {
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
+
+use super::{State, Workload};
+use crate::core::Transaction;
+
+/// key of price of feed
+fn feed_key(feed: usize) -> usize {
+    2 * feed
+}
+/// key of last quote of account
+fn quote_key(account: usize) -> usize {
+    2 * account + 1
+}
+/// txn on oracle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OracleTransaction {
+    /// read prices of feeds,write their sum as quote of account
+    Lookup {
+        /// account of quote
+        account: usize,
+        /// feeds read
+        feeds: Vec<usize>,
+    },
+    /// update price of feed
+    Update {
+        /// updated feed
+        feed: usize,
+        /// new price
+        price: usize,
+    },
+}
+impl Transaction for OracleTransaction {
+    type Key = usize;
+
+    type Value = usize;
+}
+/// read-mostly oracle lookups,lookups of different accounts only conflict with updates
+#[derive(Clone, Debug)]
+pub struct OracleWorkload {
+    /// number of price feeds
+    pub feeds: usize,
+    /// number of accounts
+    pub accounts: usize,
+    /// feeds read by each lookup
+    pub feeds_per_lookup: usize,
+    /// fraction (0..=1) of txns updating feeds
+    pub update_ratio: f64,
+}
+impl Workload for OracleWorkload {
+    type T = OracleTransaction;
+
+    type Key = usize;
+
+    type Value = usize;
+
+    fn name(&self) -> String {
+        format!(
+            "oracle (feeds={},update_ratio={})",
+            self.feeds, self.update_ratio
+        )
+    }
+
+    fn generate(
+        &self,
+        txns_num: usize,
+        seed: u64,
+    ) -> (Vec<OracleTransaction>, State<usize, usize>) {
+        let mut state = State::default();
+        for feed in 0..self.feeds {
+            state.insert(feed_key(feed), 1_000);
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let feed_distribution = Uniform::from(0..self.feeds);
+        let account_distribution = Uniform::from(0..self.accounts);
+        let price_distribution = Uniform::from(1..=10_000);
+        let txns = (0..txns_num)
+            .map(|_| {
+                if rng.gen_bool(self.update_ratio) {
+                    OracleTransaction::Update {
+                        feed: feed_distribution.sample(&mut rng),
+                        price: price_distribution.sample(&mut rng),
+                    }
+                } else {
+                    OracleTransaction::Lookup {
+                        account: account_distribution.sample(&mut rng),
+                        feeds: (0..self.feeds_per_lookup)
+                            .map(|_| feed_distribution.sample(&mut rng))
+                            .collect(),
+                    }
+                }
+            })
+            .collect();
+        (txns, state)
+    }
+
+    fn execute(
+        txn: &OracleTransaction,
+        read: &dyn Fn(&usize) -> Option<usize>,
+    ) -> Vec<(usize, usize)> {
+        match txn {
+            OracleTransaction::Lookup { account, feeds } => {
+                let quote = feeds
+                    .iter()
+                    .map(|feed| read(&feed_key(*feed)).expect("feed not found"))
+                    .sum();
+                vec![(quote_key(*account), quote)]
+            }
+            OracleTransaction::Update { feed, price } => vec![(feed_key(*feed), *price)],
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::io::{self, Read, Write};
+
+pub fn write_u8<W: Write>(w: &mut W, v: u8) -> io::Result<()> {
+    w.write_all(&[v])
+}
+pub fn read_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+pub fn write_u64<W: Write>(w: &mut W, v: u64) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+pub fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+/// length-prefixed bytes
+pub fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_u64(w, bytes.len() as u64)?;
+    w.write_all(bytes)
+}
+pub fn read_bytes<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; read_u64(r)? as usize];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+pub fn write_option<W: Write>(w: &mut W, bytes: Option<&[u8]>) -> io::Result<()> {
+    match bytes {
+        Some(bytes) => {
+            write_u8(w, 1)?;
+            write_bytes(w, bytes)
+        }
+        None => write_u8(w, 0),
+    }
+}
+pub fn read_option<R: Read>(r: &mut R) -> io::Result<Option<Vec<u8>>> {
+    match read_u8(r)? {
+        0 => Ok(None),
+        _ => Ok(Some(read_bytes(r)?)),
+    }
+}
@@ -1,6 +1,8 @@
+/// binary encoding helpers,used by multi-process protocol and storage
+pub mod codec;
 /// synchronization primitives wrappers
 mod sync;
-pub use sync::{AtomicBool, AtomicUsize, Condvar, Mutex};
+pub use sync::{AtomicBool, AtomicUsize, Condvar, Mutex, Parking, RwLock};
 
 /// transaction index (start from 0)
 pub type TxnIndex = usize;
@@ -2,7 +2,8 @@ use std::{
     ops::Deref,
     sync::{
         atomic::{AtomicBool as stdAtomicBool, AtomicUsize as stdAtomicUsize, Ordering},
-        Arc, Condvar as stdCondvar, Mutex as stdMutex, MutexGuard,
+        Arc, Condvar as stdCondvar, Mutex as stdMutex, MutexGuard, RwLock as stdRwLock,
+        RwLockReadGuard, RwLockWriteGuard,
     },
 };
 /// AtomicUsize wrapper
@@ -55,6 +56,22 @@ impl<T> Mutex<T> {
         self.0.lock().expect("lock error")
     }
 }
+/// RwLock wrapper
+pub struct RwLock<T>(stdRwLock<T>);
+impl<T> RwLock<T> {
+    pub fn new(t: T) -> Self {
+        Self(stdRwLock::new(t))
+    }
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        self.0.read().expect("lock error")
+    }
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        self.0.write().expect("lock error")
+    }
+    pub fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut().expect("lock error")
+    }
+}
 /// Condvar wrapper
 #[derive(Clone, Default)]
 pub struct Condvar {
@@ -78,3 +95,29 @@ impl Condvar {
         }
     }
 }
+/// parking lot of workers waiting for a condition shared by all of them
+#[derive(Default)]
+pub struct Parking {
+    lock: stdMutex<()>,
+    cvar: stdCondvar,
+}
+impl Parking {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// block until `ready` returns some value,it's checked under the lock so no wakeup is lost
+    pub fn park_until<R>(&self, ready: impl Fn() -> Option<R>) -> R {
+        let mut guard = self.lock.lock().expect("lock error");
+        loop {
+            if let Some(r) = ready() {
+                return r;
+            }
+            guard = self.cvar.wait(guard).expect("wait error");
+        }
+    }
+    /// wake up all parked workers,call it after changing the condition
+    pub fn unpark_all(&self) {
+        let _guard = self.lock.lock().expect("lock error");
+        self.cvar.notify_all();
+    }
+}
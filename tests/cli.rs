@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::Path,
+        process::{Command, ExitStatus},
+    };
+
+    use block_stm::test_utils::simulated::{
+        generate_txns_and_ledger, sequential_execute, Ledger, TransferTransactionOutput,
+    };
+    use either::Either;
+
+    fn run(dir: &Path, mode: &str, concurrency_level: usize) -> ExitStatus {
+        Command::new(env!("CARGO_BIN_EXE_block_stm"))
+            .arg("--genesis")
+            .arg(dir.join("genesis.json"))
+            .arg("--block")
+            .arg(dir.join("block.json"))
+            .arg("--mode")
+            .arg(mode)
+            .arg("--concurrency")
+            .arg(concurrency_level.to_string())
+            .arg("--output")
+            .arg(dir)
+            .status()
+            .expect("run block_stm error")
+    }
+
+    #[test]
+    fn cli() {
+        let dir = std::env::temp_dir().join(format!("block_stm_cli_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let seed = rand::random();
+        let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 1_000, 1, 1_000, seed);
+        fs::write(
+            dir.join("genesis.json"),
+            serde_json::to_vec(&ledger).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join("block.json"), serde_json::to_vec(&txns).unwrap()).unwrap();
+        let (s_output, _) = sequential_execute(&txns, &ledger);
+        for mode in ["both", "parallel"] {
+            let _ = fs::remove_file(dir.join("outputs.json"));
+            assert!(run(&dir, mode, num_cpus::get()).success());
+            let state: Ledger =
+                serde_json::from_slice(&fs::read(dir.join("state.json")).unwrap()).unwrap();
+            assert_eq!(
+                state,
+                ledger.clone().apply(Either::Left(s_output.clone())),
+                "state of {} mode with seed {}",
+                mode,
+                seed
+            );
+            let outputs: Vec<TransferTransactionOutput> =
+                serde_json::from_slice(&fs::read(dir.join("outputs.json")).unwrap()).unwrap();
+            assert_eq!(
+                outputs, s_output,
+                "outputs of {} mode with seed {}",
+                mode, seed
+            );
+            let stats: serde_json::Value =
+                serde_json::from_slice(&fs::read(dir.join("stats.json")).unwrap()).unwrap();
+            for field in ["parallel_ms", "execute_ms", "collect_ms", "num_aborts"] {
+                assert!(stats[field].is_number(), "{} of {} mode", field, mode);
+            }
+        }
+        assert!(!run(&dir, "parallel", 0).success());
+        assert!(!run(&dir, "parallel", num_cpus::get() + 1).success());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
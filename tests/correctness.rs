@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use block_stm::test_utils::simulated::{
-        generate_txns_and_ledger, my_parallel_execute, sequential_execute,
+        generate_txns_and_ledger, my_parallel_execute_verified, sequential_execute,
     };
     use either::Either;
 
@@ -24,13 +24,17 @@ mod tests {
         loop {
             #[cfg(feature = "tracing")]
             block_stm::rayon_info!("correctness test will start");
-            let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 10_000, 1, 1_000);
+            let seed = rand::random();
+            let (txns, ledger) = generate_txns_and_ledger(5, 1_000_000, 10_000, 1, 1_000, seed);
             let (s_output, _) = sequential_execute(&txns, &ledger);
-            let (mp_output, _) = my_parallel_execute(&txns, &ledger, num_cpus::get());
+            // repeated runs at different concurrency levels should commit the same outcome
+            let (mp_output, _) = my_parallel_execute_verified(&txns, &ledger, num_cpus::get(), 3);
             let cloned = ledger.clone();
             assert_eq!(
                 ledger.apply(Either::Left(s_output)),
-                cloned.apply(Either::Right(mp_output))
+                cloned.apply(Either::Right(mp_output)),
+                "seed {}",
+                seed
             );
             #[cfg(feature = "tracing")]
             block_stm::rayon_info!("correctness test passed");
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use block_stm::{
+        multiprocess::Coordinator,
+        test_utils::simulated::{generate_txns_and_ledger, my_parallel_execute},
+        ParallelExecutorError,
+    };
+    use either::Either;
+
+    #[test]
+    fn multiprocess() {
+        let (accounts_num, init_balance) = (5, 1_000_000);
+        let coordinator = Coordinator::spawn(
+            env!("CARGO_BIN_EXE_block_stm_worker"),
+            [accounts_num.to_string(), init_balance.to_string()],
+            num_cpus::get(),
+        )
+        .expect("spawn workers error");
+        // workers are reused across blocks
+        for _ in 0..3 {
+            let seed = rand::random();
+            let (txns, ledger) =
+                generate_txns_and_ledger(accounts_num, init_balance, 1_000, 1, 1_000, seed);
+            let (p_output, _) = my_parallel_execute(&txns, &ledger, num_cpus::get());
+            let mp_output = coordinator
+                .execute_transactions(&txns)
+                .unwrap_or_else(|e| panic!("seed {}: {}", seed, e));
+            let cloned = ledger.clone();
+            assert_eq!(
+                ledger.apply(Either::Right(p_output)),
+                cloned.apply(Either::Right(mp_output)),
+                "seed {}",
+                seed
+            );
+        }
+    }
+    #[test]
+    fn crashed_worker() {
+        // worker panics on bad arguments,before serving any transaction
+        let coordinator = Coordinator::spawn(
+            env!("CARGO_BIN_EXE_block_stm_worker"),
+            ["bad", "arguments"],
+            num_cpus::get(),
+        )
+        .expect("spawn workers error");
+        // each execution respawns worker to retry,so keep block small
+        let (txns, _) = generate_txns_and_ledger(5, 1_000_000, 10, 1, 1_000, 0);
+        for _ in 0..2 {
+            assert!(matches!(
+                coordinator.execute_transactions(&txns),
+                Err(ParallelExecutorError::Execution { .. })
+            ));
+        }
+    }
+    #[test]
+    fn respawned_worker() {
+        let (accounts_num, init_balance) = (5, 1_000_000);
+        // each worker crashes after reading a few dozens of transactions
+        let coordinator = Coordinator::spawn(
+            env!("CARGO_BIN_EXE_block_stm_worker"),
+            [accounts_num, init_balance, 2_048].map(|arg| arg.to_string()),
+            num_cpus::get(),
+        )
+        .expect("spawn workers error");
+        for _ in 0..2 {
+            let seed = rand::random();
+            let (txns, ledger) =
+                generate_txns_and_ledger(accounts_num, init_balance, 1_000, 1, 1_000, seed);
+            let (p_output, _) = my_parallel_execute(&txns, &ledger, num_cpus::get());
+            let mp_output = coordinator
+                .execute_transactions(&txns)
+                .unwrap_or_else(|e| panic!("seed {}: {}", seed, e));
+            let cloned = ledger.clone();
+            assert_eq!(
+                ledger.apply(Either::Right(p_output)),
+                cloned.apply(Either::Right(mp_output)),
+                "seed {}",
+                seed
+            );
+        }
+    }
+    #[test]
+    fn overlapping_blocks() {
+        let (accounts_num, init_balance) = (5, 1_000_000);
+        let coordinator = Coordinator::spawn(
+            env!("CARGO_BIN_EXE_block_stm_worker"),
+            [accounts_num.to_string(), init_balance.to_string()],
+            num_cpus::get(),
+        )
+        .expect("spawn workers error");
+        let seed = rand::random();
+        let (txns, _) = generate_txns_and_ledger(accounts_num, init_balance, 1_000, 1, 1_000, seed);
+        crossbeam::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|_| {
+                    coordinator
+                        .execute_transactions(&txns)
+                        .unwrap_or_else(|e| panic!("seed {}: {}", seed, e))
+                });
+            }
+        })
+        .unwrap();
+    }
+}